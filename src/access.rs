@@ -0,0 +1,210 @@
+// Register access abstraction for memory-mapped and bus-based register files
+// Copyright 2018 Ryan Kurte
+
+use ::cell::VolatileCell;
+use ::register::{Register, RegisterType};
+
+#[doc = "RegisterAccess trait abstracts reading and writing register values at an address"]
+#[doc = "This allows registers to be used over memory-mapped, bus-based, or fake register files"]
+pub trait RegisterAccess<T> {
+    fn read(&self, addr: usize) -> T;
+    fn write(&mut self, addr: usize, v: T);
+}
+
+#[doc = "Mmio implements RegisterAccess using volatile reads and writes of memory-mapped registers"]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Mmio;
+
+impl <T: RegisterType<T>>RegisterAccess<T> for Mmio {
+    fn read(&self, addr: usize) -> T {
+        VolatileCell::new(addr).get()
+    }
+
+    fn write(&mut self, addr: usize, v: T) {
+        Register(addr, v).write_no_verify()
+    }
+}
+
+impl <T: RegisterType<T>>Register<T> {
+    #[doc = "Reads the register value using the provided accessor and returns a new instance with internal value set"]
+    pub fn read_with(&self, access: &mut impl RegisterAccess<T>) -> Register<T> {
+        Register(self.0, access.read(self.0))
+    }
+
+    #[doc = "Polls the register using the provided accessor until the field with the provided mask and shift matches the target"]
+    #[doc = "Returns `Err(())` if the field does not match within `max_iters` reads, as with `wait_for_masked`"]
+    #[allow(clippy::result_unit_err)]
+    pub fn wait_for_masked_with(&self, access: &mut impl RegisterAccess<T>, shift: T, mask: T, target: T, max_iters: u32) -> Result<(), ()> {
+        for _ in 0..max_iters {
+            if self.read_with(access).get_masked(shift, mask) == target {
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+
+    #[doc = "Writes the internal value using the provided accessor only if it differs from the current register value"]
+    #[doc = "Returns true if the write was performed"]
+    pub fn write_if_changed_with(self, access: &mut impl RegisterAccess<T>) -> bool {
+        if access.read(self.0) == self.1 {
+            return false;
+        }
+        self.write_with(access);
+        true
+    }
+
+    #[doc = "Writes the internal value to the register using the provided accessor"]
+    #[doc = "With the `verify-writes` feature enabled in builds with `debug_assertions`, the value is read back as with `write`"]
+    pub fn write_with(self, access: &mut impl RegisterAccess<T>) {
+        #[cfg(all(feature = "verify-writes", debug_assertions))]
+        let (addr, val) = (self.0, self.1);
+        self.write_with_no_verify(access);
+        #[cfg(all(feature = "verify-writes", debug_assertions))]
+        {
+            let read = access.read(addr);
+            assert!(read == val, "register write verify failed at 0x{:x}, wrote 0x{:x} read 0x{:x}",
+                addr, Into::<u64>::into(val), Into::<u64>::into(read));
+        }
+    }
+
+    #[doc = "Writes the internal value to the register using the provided accessor without read-back verification"]
+    #[doc = "This is equivalent to `write_with` unless the `verify-writes` feature is enabled"]
+    pub fn write_with_no_verify(self, access: &mut impl RegisterAccess<T>) {
+        access.write(self.0, self.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::mem;
+    use ::access::{RegisterAccess, Mmio};
+    use ::register::{Register, RegisterType};
+
+    // Fake register file backed by an array, indexed by address / register width
+    struct Fake<T>([T; 256]);
+
+    impl <T: RegisterType<T>>RegisterAccess<T> for Fake<T> {
+        fn read(&self, addr: usize) -> T {
+            self.0[addr / mem::size_of::<T>()]
+        }
+
+        fn write(&mut self, addr: usize, v: T) {
+            self.0[addr / mem::size_of::<T>()] = v;
+        }
+    }
+
+    #[test]
+    fn fake() {
+        let mut f = Fake([0u32; 256]);
+        f.0[2] = 0x00F0;
+
+        let r = Register::<u32>::new(0x08).read_with(&mut f);
+        assert_eq!(0x00F0, r.value());
+
+        r.set_bit(0, true).write_with(&mut f);
+        assert_eq!(0x00F1, f.0[2]);
+        assert_eq!(0, f.0[3]);
+    }
+
+    // Fake read-only register file that ignores writes
+    #[cfg(feature = "verify-writes")]
+    struct ReadOnly<T>(T);
+
+    #[cfg(feature = "verify-writes")]
+    impl <T: RegisterType<T>>RegisterAccess<T> for ReadOnly<T> {
+        fn read(&self, _addr: usize) -> T {
+            self.0
+        }
+
+        fn write(&mut self, _addr: usize, _v: T) {}
+    }
+
+    #[cfg(feature = "verify-writes")]
+    #[test]
+    fn verify_writes() {
+        let mut f = Fake([0u32; 256]);
+        Register::<u32>::new(0x08).set(0xAA).write_with(&mut f);
+        assert_eq!(0xAA, f.0[2]);
+
+        let mut mem: u16 = 0;
+        Register::<u16>::new(&mut mem as *mut u16 as usize).set(0x55).write();
+        assert_eq!(0x55, mem);
+    }
+
+    #[cfg(all(feature = "verify-writes", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "register write verify failed")]
+    fn verify_writes_read_only() {
+        Register::<u32>::new(0x08).set(0xAA).write_with(&mut ReadOnly(0u32));
+    }
+
+    #[cfg(feature = "verify-writes")]
+    #[test]
+    fn verify_writes_no_verify() {
+        let mut f = ReadOnly(0u32);
+        Register::<u32>::new(0x08).set(0xAA).write_with_no_verify(&mut f);
+        assert_eq!(0, f.0);
+    }
+
+    // Fake register file that counts writes
+    struct Counting(u32, usize);
+
+    impl RegisterAccess<u32> for Counting {
+        fn read(&self, _addr: usize) -> u32 {
+            self.0
+        }
+
+        fn write(&mut self, _addr: usize, v: u32) {
+            self.0 = v;
+            self.1 += 1;
+        }
+    }
+
+    #[test]
+    fn write_if_changed() {
+        let mut f = Counting(0xAA, 0);
+        assert_eq!(false, Register::<u32>::new(0).set(0xAA).write_if_changed_with(&mut f));
+        assert_eq!(0, f.1);
+        assert_eq!(true, Register::<u32>::new(0).set(0xBB).write_if_changed_with(&mut f));
+        assert_eq!((0xBB, 1), (f.0, f.1));
+    }
+
+    // Fake register file whose value advances on each read
+    struct Stepping(Cell<u16>);
+
+    impl RegisterAccess<u16> for Stepping {
+        fn read(&self, _addr: usize) -> u16 {
+            let v = self.0.get();
+            self.0.set(v + (1 << 4));
+            v
+        }
+
+        fn write(&mut self, _addr: usize, v: u16) {
+            self.0.set(v)
+        }
+    }
+
+    #[test]
+    fn wait_for_masked() {
+        let r = Register::<u16>::new(0);
+        // The field reads 0b11 on the fourth poll
+        assert_eq!(Ok(()), r.wait_for_masked_with(&mut Stepping(Cell::new(0)), 4, 0b11, 0b11, 4));
+        assert_eq!(Err(()), r.wait_for_masked_with(&mut Stepping(Cell::new(0)), 4, 0b11, 0b11, 3));
+
+        let mut f = Stepping(Cell::new(0));
+        assert_eq!(Ok(()), r.wait_for_masked_with(&mut f, 4, 0b11, 0b00, 1));
+        assert_eq!(1 << 4, f.0.get());
+    }
+
+    #[test]
+    fn mmio() {
+        let mut mem: u16 = 0x1234;
+        let r = Register::<u16>::new(&mut mem as *mut u16 as usize);
+        let r = r.read_with(&mut Mmio);
+        assert_eq!(0x1234, r.value());
+
+        r.set(0x5678).write_with(&mut Mmio);
+        assert_eq!(0x5678, mem);
+    }
+}
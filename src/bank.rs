@@ -0,0 +1,197 @@
+// Register bank types for indexed access to contiguous registers
+// Copyright 2018 Ryan Kurte
+
+use core::marker::PhantomData;
+use core::mem;
+
+use ::register::{Register, RegisterType};
+
+// Register bank helper structure
+// This computes register addresses from a base and a compile-time element count.
+#[doc = "RegisterBank type describes a fixed number of contiguous registers of the same type"]
+#[derive(Debug, PartialEq, Clone)]
+pub struct RegisterBank<T: RegisterType<T>, const N: usize> (pub usize, PhantomData<T>);
+
+impl <T: RegisterType<T>, const N: usize>RegisterBank<T, N> {
+    #[doc = "Creates a new register bank of the provided type with the specified base address"]
+    pub fn new(addr: usize) -> RegisterBank<T, N> {
+        RegisterBank(addr, PhantomData)
+    }
+
+    #[doc = "Returns the number of registers in the bank"]
+    pub const fn len() -> usize {
+        N
+    }
+
+    #[doc = "Fetches the register at the provided index"]
+    #[doc = "Panics if the index is outside the bank"]
+    pub fn get(&mut self, i: usize) -> Register<T> {
+        assert!(i < N, "register bank index out of range");
+        Register::new(self.0 + i * mem::size_of::<T>())
+    }
+
+    #[doc = "Fetches the register at the provided constant index, checked at compile time"]
+    #[doc = "```compile_fail"]
+    #[doc = "# use embedded_builder::bank::RegisterBank;"]
+    #[doc = "let mut b = RegisterBank::<u32, 4>::new(0x1000);"]
+    #[doc = "let r = b.get_const::<4>();"]
+    #[doc = "```"]
+    pub fn get_const<const I: usize>(&mut self) -> Register<T> {
+        const { assert!(I < N, "register bank index out of range") };
+        Register::new(self.0 + I * mem::size_of::<T>())
+    }
+}
+
+// Register array helper structure
+// This computes register addresses from a base, stride and element count provided at runtime.
+#[doc = "RegisterArray type describes a number of registers of the same type at a fixed stride"]
+#[derive(Debug, PartialEq, Clone)]
+pub struct RegisterArray<T: RegisterType<T>> {
+    base: usize,
+    stride: usize,
+    count: usize,
+    _t: PhantomData<T>,
+}
+
+impl <T: RegisterType<T>>RegisterArray<T> {
+    #[doc = "Creates a new register array of `count` registers, each `stride` bytes apart, from the base address"]
+    pub fn new(base: usize, stride: usize, count: usize) -> RegisterArray<T> {
+        RegisterArray{ base, stride, count, _t: PhantomData }
+    }
+
+    #[doc = "Returns the number of registers in the array"]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    #[doc = "Fetches the register at the provided index"]
+    #[doc = "Panics if the index is outside the array or the address overflows"]
+    pub fn index(&self, i: usize) -> Register<T> {
+        assert!(i < self.count, "register array index out of range");
+        self.try_index(i).expect("register array address overflow")
+    }
+
+    #[doc = "Fetches the register at the provided index, returning `None` if the index is outside the array"]
+    #[doc = "or computing the address overflows"]
+    pub fn try_index(&self, i: usize) -> Option<Register<T>> {
+        if i >= self.count {
+            return None;
+        }
+        i.checked_mul(self.stride)
+            .and_then(|offset| self.base.checked_add(offset))
+            .map(Register::new)
+    }
+}
+
+#[doc = "ChannelLayout describes how the registers of multiple channels are arranged"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChannelLayout {
+    #[doc = "Each register is repeated for every channel before the next register, ie. A0, A1, .., B0, B1, .."]
+    Interleaved,
+    #[doc = "Each channel has a contiguous block of registers the provided number of bytes apart, ie. A0, B0, .., A1, B1, .."]
+    Blocked(usize),
+}
+
+// Channel register helper structure
+// This computes register addresses for one of N channels from a base and per-channel register offset.
+#[doc = "ChannelRegs type describes the registers of channel CH of a peripheral with N channels"]
+#[doc = "```compile_fail"]
+#[doc = "# use embedded_builder::bank::{ChannelRegs, ChannelLayout};"]
+#[doc = "let c = ChannelRegs::<u32, 4, 4>::new(0x1000, ChannelLayout::Interleaved);"]
+#[doc = "```"]
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChannelRegs<T: RegisterType<T>, const CH: usize, const N: usize> {
+    base: usize,
+    layout: ChannelLayout,
+    _t: PhantomData<T>,
+}
+
+impl <T: RegisterType<T>, const CH: usize, const N: usize>ChannelRegs<T, CH, N> {
+    #[doc = "Creates channel registers with the provided base address and layout, the channel is checked at compile time"]
+    pub fn new(base: usize, layout: ChannelLayout) -> ChannelRegs<T, CH, N> {
+        const { assert!(CH < N, "channel out of range") };
+        ChannelRegs{ base, layout, _t: PhantomData }
+    }
+
+    #[doc = "Fetches the channel register at the provided offset within a single channel's register map"]
+    #[doc = "For interleaved layouts this is `base + offset * N + CH * size`, for blocked layouts `base + CH * stride + offset`"]
+    pub fn get(&self, offset: usize) -> Register<T> {
+        let addr = match self.layout {
+            ChannelLayout::Interleaved => self.base + offset * N + CH * mem::size_of::<T>(),
+            ChannelLayout::Blocked(stride) => self.base + CH * stride + offset,
+        };
+        Register::new(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::bank::{RegisterBank, RegisterArray, ChannelRegs, ChannelLayout};
+
+    #[test]
+    fn len() {
+        assert_eq!(4, RegisterBank::<u32, 4>::len());
+    }
+
+    #[test]
+    fn get() {
+        let mut b = RegisterBank::<u32, 4>::new(0x1000);
+        assert_eq!(0x1000, b.get(0).addr());
+        assert_eq!(0x100C, b.get(3).addr());
+
+        let mut b = RegisterBank::<u16, 4>::new(0x1000);
+        assert_eq!(0x1006, b.get(3).addr());
+    }
+
+    #[test]
+    #[should_panic(expected = "register bank index out of range")]
+    fn get_out_of_range() {
+        let mut b = RegisterBank::<u32, 4>::new(0x1000);
+        b.get(4);
+    }
+
+    #[test]
+    fn get_const() {
+        let mut b = RegisterBank::<u32, 4>::new(0x1000);
+        assert_eq!(0x1008, b.get_const::<2>().addr());
+    }
+
+    #[test]
+    fn array_index() {
+        let a = RegisterArray::<u32>::new(0x1000, 0x10, 4);
+        assert_eq!(4, a.count());
+        assert_eq!(0x1000, a.index(0).addr());
+        assert_eq!(0x1030, a.index(3).addr());
+        assert_eq!(Some(0x1020), a.try_index(2).map(|r| r.addr()));
+    }
+
+    #[test]
+    fn array_try_index() {
+        let a = RegisterArray::<u32>::new(0x1000, 0x10, 4);
+        assert_eq!(None, a.try_index(4));
+
+        let a = RegisterArray::<u32>::new(usize::MAX - 0x100, 0x100, 4);
+        assert_eq!(Some(usize::MAX - 0x100), a.try_index(0).map(|r| r.addr()));
+        assert_eq!(None, a.try_index(2));
+
+        let a = RegisterArray::<u32>::new(0x1000, usize::MAX / 2, 4);
+        assert_eq!(None, a.try_index(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "register array index out of range")]
+    fn array_index_out_of_range() {
+        RegisterArray::<u32>::new(0x1000, 0x10, 4).index(4);
+    }
+
+    #[test]
+    fn channel_regs() {
+        let c = ChannelRegs::<u32, 1, 4>::new(0x1000, ChannelLayout::Interleaved);
+        assert_eq!(0x1004, c.get(0x0).addr());
+        assert_eq!(0x1024, c.get(0x8).addr());
+
+        let c = ChannelRegs::<u32, 1, 4>::new(0x1000, ChannelLayout::Blocked(0x100));
+        assert_eq!(0x1100, c.get(0x0).addr());
+        assert_eq!(0x1108, c.get(0x8).addr());
+    }
+}
@@ -0,0 +1,87 @@
+// Volatile cell type for plain typed memory-mapped access
+// Copyright 2018 Ryan Kurte
+
+use core::marker::PhantomData;
+use core::ptr::{read_volatile, write_volatile};
+
+// Volatile cell helper structure
+// This is the primitive volatile access used by registers, regions, and the Mmio accessor.
+#[doc = "VolatileCell type wraps an address for typed volatile reads and writes, without any field helpers"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct VolatileCell<T> (usize, PhantomData<T>);
+
+impl <T>VolatileCell<T> {
+    #[doc = "Creates a new volatile cell at the provided address"]
+    pub fn new(addr: usize) -> VolatileCell<T> {
+        VolatileCell(addr, PhantomData)
+    }
+
+    #[doc = "Returns the cell address"]
+    pub fn addr(&self) -> usize {
+        self.0
+    }
+
+    #[doc = "Reads the value at the cell address using a volatile read"]
+    pub fn get(&self) -> T {
+        unsafe {
+            read_volatile(self.0 as *const T)
+        }
+    }
+
+    #[doc = "Writes the value to the cell address using a volatile write"]
+    pub fn set(&mut self, v: T) {
+        unsafe {
+            write_volatile(self.0 as *mut T, v)
+        }
+    }
+}
+
+// Volatile reference helper structure
+// This borrows an element of a region for volatile access during iteration.
+#[doc = "VolatileRef type is a proxy for an element of a region, performing volatile reads and writes of the element"]
+#[derive(Debug, PartialEq)]
+pub struct VolatileRef<'a, T> (VolatileCell<T>, PhantomData<&'a mut T>);
+
+impl <'a, T>VolatileRef<'a, T> {
+    #[doc = "Creates a new volatile reference to the provided element"]
+    pub fn new(r: &'a mut T) -> VolatileRef<'a, T> {
+        VolatileRef(VolatileCell::new(r as *mut T as usize), PhantomData)
+    }
+
+    #[doc = "Reads the element using a volatile read"]
+    pub fn read(&self) -> T {
+        self.0.get()
+    }
+
+    #[doc = "Writes the element using a volatile write"]
+    pub fn write(&self, v: T) {
+        VolatileCell::new(self.0.addr()).set(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::cell::{VolatileCell, VolatileRef};
+
+    #[test]
+    fn get_set() {
+        let mut mem = [0u32; 3];
+        let mut c = VolatileCell::<u32>::new(&mut mem[1] as *mut u32 as usize);
+        assert_eq!(0, c.get());
+
+        c.set(0xAABB_CCDD);
+        assert_eq!(0xAABB_CCDD, c.get());
+        assert_eq!([0, 0xAABB_CCDD, 0], mem);
+    }
+
+    #[test]
+    fn volatile_ref() {
+        let mut mem = 10u16;
+        {
+            let r = VolatileRef::new(&mut mem);
+            r.write(r.read() + 1);
+            assert_eq!(11, r.read());
+        }
+        assert_eq!(11, mem);
+    }
+}
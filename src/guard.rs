@@ -0,0 +1,76 @@
+// Scoped register guard for RAII style register modification
+// Copyright 2018 Ryan Kurte
+
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use ::register::{Register, RegisterType};
+
+// Register guard helper structure
+// This holds a read register value and writes it back when dropped.
+#[doc = "RegisterGuard type holds a register value read by `Register::edit`, writing it back to the register when dropped"]
+#[doc = "The guard dereferences to the read register for inspection and in-place modification,"]
+#[doc = "and `update` applies builder methods to the held value."]
+#[doc = ""]
+#[doc = "Note that the write is also performed if the guard is dropped while unwinding from a panic,"]
+#[doc = "so partial edits will be written, and a panic during that write (ie. with `verify-writes`) will abort"]
+pub struct RegisterGuard<'a, T: RegisterType<T>> {
+    reg: Register<T>,
+    _borrow: PhantomData<&'a mut ()>,
+}
+
+impl <T: RegisterType<T>>Register<T> {
+    #[doc = "Reads the register and returns a guard that writes the (modified) value back when dropped"]
+    pub fn edit(&mut self) -> RegisterGuard<'_, T> {
+        RegisterGuard{ reg: self.read(), _borrow: PhantomData }
+    }
+}
+
+impl <'a, T: RegisterType<T>>RegisterGuard<'a, T> {
+    #[doc = "Applies the provided builder function to the held register value"]
+    pub fn update<F: FnOnce(Register<T>) -> Register<T>>(&mut self, f: F) {
+        self.reg = f(self.reg.clone());
+    }
+}
+
+impl <'a, T: RegisterType<T>>Deref for RegisterGuard<'a, T> {
+    type Target = Register<T>;
+
+    fn deref(&self) -> &Register<T> {
+        &self.reg
+    }
+}
+
+impl <'a, T: RegisterType<T>>DerefMut for RegisterGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Register<T> {
+        &mut self.reg
+    }
+}
+
+impl <'a, T: RegisterType<T>>Drop for RegisterGuard<'a, T> {
+    fn drop(&mut self) {
+        self.reg.clone().write()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr::read_volatile;
+    use ::register::Register;
+
+    #[test]
+    fn write_on_drop() {
+        let mut mem: u16 = 0x0100;
+        let p = &mut mem as *mut u16;
+        let mut r = Register::<u16>::new(p as usize);
+
+        {
+            let mut g = r.edit();
+            assert_eq!(0x0100, g.value());
+            g.update(|r| r.set_bit(0, true));
+            g.1 |= 0x0010;
+            assert_eq!(0x0100, unsafe { read_volatile(p) });
+        }
+        assert_eq!(0x0111, unsafe { read_volatile(p) });
+    }
+}
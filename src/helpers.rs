@@ -24,13 +24,19 @@ macro_rules! field_trait {
         fn $name(&self) -> bool;
     };
     (w, $name: ident, $field: tt, $t:ty, $shift: expr) => {
-        fn $name(mut self, v: bool) -> Self;
+        fn $name(self, v: bool) -> Self;
     };
     (r, $name: ident, $field:tt, $t:ty, $shift:expr, $mask:expr) => {
         fn $name(&self) -> $t;
     };
     (w, $name: ident, $field:tt, $t:ty, $shift:expr, $mask:expr) => {
-        fn $name(mut self, v: $t) -> Self;
+        fn $name(self, v: $t) -> Self;
+    };
+    (rs, $name: ident, $field:tt, $t:ty, $shift:expr, $mask:expr) => {
+        fn $name(&self, factor: $t) -> $crate::register::Scaled<$t>;
+    };
+    (ws, $name: ident, $field:tt, $t:ty, $shift:expr, $mask:expr) => {
+        fn $name(self, v: $crate::register::Scaled<$t>, factor: $t) -> Self;
     };
 }
 
@@ -62,27 +68,363 @@ macro_rules! field_method {
             self
         }
     };
+    (rs, $name: ident, $field: tt, $t:ty, $shift: expr, $mask: expr) => {
+        fn $name(&self, factor: $t) -> $crate::register::Scaled<$t> {
+            $crate::register::Scaled(read_masked!(self.$field, $shift, $mask) * factor)
+        }
+    };
+    (ws, $name: ident, $field: tt, $t:ty, $shift: expr, $mask: expr) => {
+        fn $name(mut self, v: $crate::register::Scaled<$t>, factor: $t) -> Self {
+            write_masked!(self.$field, $shift, $mask, v.0 / factor);
+            self
+        }
+    };
+}
+
+#[doc = "Generates a FieldSpec constant for a provided field type, if a constant name is provided"]
+#[macro_export]
+macro_rules! field_const {
+    ($t:ty, [], $( $args: expr ),*) => {};
+    ($t:ty, [$cname: ident], $shift: expr) => {
+        const $cname: $crate::register::FieldSpec<$t> = $crate::register::FieldSpec { shift: $shift, mask: 1 };
+    };
+    ($t:ty, [$cname: ident], $shift: expr, $mask: expr) => {
+        const $cname: $crate::register::FieldSpec<$t> = $crate::register::FieldSpec { shift: $shift, mask: $mask };
+    };
+}
+
+#[doc = "Generates a presence predicate for a masked read field, if a predicate name is provided"]
+#[macro_export]
+macro_rules! field_present {
+    (trait, $op: ident, [], $( $args: expr ),*) => {};
+    (trait, r, [$pname: ident], $shift: expr, $mask: expr) => {
+        #[doc = "Returns true if any bit of the field is set"]
+        fn $pname(&self) -> bool;
+    };
+    (trait, $op: ident, [$pname: ident], $( $args: expr ),*) => {
+        compile_error!(concat!("presence predicate `", stringify!($pname), "` requires a masked `r` field"));
+    };
+    (impl, $op: ident, [], $field: tt, $( $args: expr ),*) => {};
+    (impl, r, [$pname: ident], $field: tt, $shift: expr, $mask: expr) => {
+        fn $pname(&self) -> bool {
+            self.$field & ($mask << $shift) != 0
+        }
+    };
+    (impl, $op: ident, [$pname: ident], $field: tt, $( $args: expr ),*) => {};
+}
+
+#[doc = "Generates a decoded field formatter for a register when the debug flag is provided"]
+#[macro_export]
+macro_rules! register_debug {
+    (trait, [], $reg: ident, $t: ty) => {};
+    (trait, [debug], $reg: ident, $t: ty) => {
+        #[doc = "Returns a formatter that displays the decoded readable fields of the register"]
+        fn debug_fields(&self) -> $crate::register::RegisterFmt<'_, $t>;
+    };
+    (impl, [], $reg: ident, $t: ty, [$( $op: ident $name: ident ),*]) => {};
+    (impl, [debug], $reg: ident, $t: ty, [$( $op: ident $name: ident ),*]) => {
+        fn debug_fields(&self) -> $crate::register::RegisterFmt<'_, $t> {
+            $crate::register::RegisterFmt(self, |r, f| {
+                let mut d = f.debug_struct(stringify!($reg));
+                $( field_debug!(d, r, $reg, $t, $op, $name); )*
+                d.finish()
+            })
+        }
+    };
+}
+
+#[doc = "Adds a readable field to a register debug formatter"]
+#[macro_export]
+macro_rules! field_debug {
+    ($d: ident, $r: ident, $reg: ident, $t: ty, r, $name: ident) => {
+        $d.field(stringify!($name), &<$crate::register::Register<$t> as $reg>::$name($r));
+    };
+    ($d: ident, $r: ident, $reg: ident, $t: ty, $op: ident, $name: ident) => {};
+}
+
+#[doc = "Checks at compile time that a field fits within the register type, and that a field mask is non-zero and contiguous from bit 0"]
+#[macro_export]
+macro_rules! field_check {
+    ($name: ident, $t: ty, $shift: expr) => {
+        const _: () = assert!(($shift as u32) < <$t>::BITS,
+            concat!("field `", stringify!($name), "` does not fit within the register type"));
+    };
+    ($name: ident, $t: ty, $shift: expr, $mask: expr) => {
+        const _: () = assert!(($mask as $t) != 0 && ($mask as $t) & ($mask as $t).wrapping_add(1) == 0,
+            concat!("field `", stringify!($name), "` mask must be contiguous from bit 0"));
+        const _: () = assert!(($shift as u32) + (u64::BITS - ($mask as u64).leading_zeros()) <= <$t>::BITS,
+            concat!("field `", stringify!($name), "` does not fit within the register type"));
+    };
+}
+
+#[doc = "Checks a field operation is permitted by the register access mode"]
+#[macro_export]
+macro_rules! field_access {
+    (rw, $op: ident, $name: ident) => {};
+    (ro, r, $name: ident) => {};
+    (ro, rs, $name: ident) => {};
+    (wo, ws, $name: ident) => {};
+    (wo, w, $name: ident) => {};
+    (ro, $op: ident, $name: ident) => {
+        compile_error!(concat!("write accessor `", stringify!($name), "` on read-only register"));
+    };
+    (wo, $op: ident, $name: ident) => {
+        compile_error!(concat!("read accessor `", stringify!($name), "` on write-only register"));
+    };
 }
 
 #[doc = "Creates accessor traits and implementations for a given register\n"]
-#[doc = "`register!(name, type, [r/w/rw, field name, field in object (ie. 1 for register tuple), return type, (mask for non-bool types)];`"]
+#[doc = "`register!(name, type, [r/w/rs/ws, field name, field in object (ie. 1 for register tuple), return type, (mask for non-bool types)];`"]
+#[doc = "An optional reset value may be provided with `register!(name, type, reset = value, [...])`\n"]
+#[doc = "Fields with the `rs` and `ws` operations read and write a `Scaled` value, multiplying the decoded field by"]
+#[doc = "a factor on read and dividing by it on write, ie. to convert a clock divider field to a frequency."]
+#[doc = "This arithmetic uses the register type, so reads panic on overflow in debug builds (wrapping in release builds),"]
+#[doc = "and writes truncate values that are not a multiple of the factor and panic if the factor is zero"]
+#[doc = "```"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(CLKDIV, u32, ["]
+#[doc = "    rs, freq, 1, u32, 4, 0xF;"]
+#[doc = "    ws, set_freq, 1, u32, 4, 0xF;"]
+#[doc = "]);"]
+#[doc = "# fn main() { assert_eq!(3_000_000, Register::<u32>(0, 0x30).freq(1_000_000).0); }"]
+#[doc = "```\n"]
+#[doc = "Any field may be suffixed with `=> NAME` to emit a `FieldSpec` constant describing its shift and mask"]
+#[doc = "(a mask of 1 for single bit fields), accessible as `<Register<type> as name>::NAME`. Constants are named"]
+#[doc = "explicitly rather than as generated `FIELD_SHIFT` and `FIELD_MASK` pairs, as `macro_rules!` cannot"]
+#[doc = "derive new identifiers from the field name"]
+#[doc = "```"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(CTRL, u32, ["]
+#[doc = "    w, set_en, 1, bool, 0 => EN;"]
+#[doc = "    w, set_div, 1, u32, 4, 0b1111 => DIV;"]
+#[doc = "]);"]
+#[doc = "# fn main() {"]
+#[doc = "let div = <Register<u32> as CTRL>::DIV;"]
+#[doc = "assert_eq!((4, 0b1111), (div.shift, div.mask));"]
+#[doc = "assert_eq!(0x30, Register::<u32>(0, 0).set_masked(div.shift, div.mask, 3).value());"]
+#[doc = "# }"]
+#[doc = "```\n"]
+#[doc = "Masked `r` fields may be suffixed with `=> ? name` to also generate a presence predicate, returning"]
+#[doc = "true if any bit of the field is set, after the `=> NAME` suffix if both are provided"]
+#[doc = "```"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(CTRL, u32, ["]
+#[doc = "    r, channel, 1, u32, 4, 0b111 => ? channel_present;"]
+#[doc = "]);"]
+#[doc = "# fn main() { assert!(Register::<u32>(0, 0x40).channel_present()); }"]
+#[doc = "```\n"]
+#[doc = "Field masks must be contiguous from bit 0 (ie. `0b111`, not `0b101` or a pre-shifted mask), this is checked at compile time"]
+#[doc = "```compile_fail"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(CTRL, u32, ["]
+#[doc = "    r, mode, 1, u32, 4, 0b101;"]
+#[doc = "]);"]
+#[doc = "# fn main() {}"]
+#[doc = "```\n"]
+#[doc = "Registers are read-write by default, `register!(name, type, ro, [...])` or `register!(name, type, wo, [...])`"]
+#[doc = "restrict a register to read or write accessors only, rejecting other accessors at compile time\n"]
+#[doc = "```"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(STATUS, u32, ro, ["]
+#[doc = "    r, ready, 1, bool, 0;"]
+#[doc = "]);"]
+#[doc = "# fn main() { assert!(Register::<u32>(0, 1).ready()); }"]
+#[doc = "```"]
+#[doc = "```compile_fail"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(STATUS, u32, ro, ["]
+#[doc = "    r, ready, 1, bool, 0;"]
+#[doc = "    w, set_ready, 1, bool, 0;"]
+#[doc = "]);"]
+#[doc = "# fn main() {}"]
+#[doc = "```\n"]
+#[doc = "Fields must fit within the register type, this is checked at compile time"]
+#[doc = "```compile_fail"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(CTRL, u16, ["]
+#[doc = "    r, mode, 1, u16, 20, 0b11;"]
+#[doc = "]);"]
+#[doc = "# fn main() {}"]
+#[doc = "```\n"]
+#[doc = "The register width may also be declared with `register!(name, type, width = bits, [...])` to check the register type matches"]
+#[doc = "```"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(CTRL, u32, width = 32, ["]
+#[doc = "    r, mode, 1, u32, 4, 0b11;"]
+#[doc = "]);"]
+#[doc = "# fn main() { assert_eq!(2, Register::<u32>(0, 0x20).mode()); }"]
+#[doc = "```"]
+#[doc = "```compile_fail"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(CTRL, u16, width = 32, ["]
+#[doc = "    r, mode, 1, u16, 4, 0b11;"]
+#[doc = "]);"]
+#[doc = "# fn main() {}"]
+#[doc = "```\n"]
+#[doc = "`register!(name, type, debug, ...)` additionally generates `debug_fields()`, returning a `Debug` formatter"]
+#[doc = "that displays each readable field and its decoded value"]
+#[doc = "```"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register!(CTRL, u16, debug, ["]
+#[doc = "    r, en, 1, bool, 0;"]
+#[doc = "    r, div, 1, u16, 4, 0b1111;"]
+#[doc = "]);"]
+#[doc = "# fn main() {"]
+#[doc = "let r = Register::<u16>(0, 0x0031);"]
+#[doc = "assert_eq!(\"CTRL { en: true, div: 3 }\", format!(\"{:?}\", r.debug_fields()));"]
+#[doc = "# }"]
+#[doc = "```"]
 #[macro_export]
 macro_rules! register {
     (
-        $reg:ident, $t:ty, [ $( $op:ident, $name:ident, $field:tt, $type:ty, $( $args:expr ),* );* ;]
+        @impl $access:ident, [$( $dbg:tt )?], $reg:ident, $t:ty, $( width = $width:expr, )? $( reset = $reset:expr, )? [ $( $op:ident, $name:ident, $field:tt, $type:ty, $( $args:expr ),* $( => $cname:ident )? $( => ? $pname:ident )? );* ;]
     ) => {
+        $( const _: () = assert!($width == <$t>::BITS, concat!("register `", stringify!($reg), "` type does not match the declared width")); )?
+        $( field_access!($access, $op, $name); )*
+        $( field_check!($name, $t, $( $args ),*); )*
         pub trait $reg {
+            $( field_const!($t, [$( $cname )?], $( $args ),*); )*
             $( field_trait!($op, $name, $field, $type, $( $args ),* ); )*
+            $( field_present!(trait, $op, [$( $pname )?], $( $args ),*); )*
+            $(
+                #[doc = concat!("Returns the register reset value (", stringify!($reset), ")")]
+                fn reset_value() -> $t;
+                #[doc = "Sets the internal value of the register to the reset value"]
+                fn reset(self) -> Self;
+            )?
+            register_debug!(trait, [$( $dbg )?], $reg, $t);
         }
         impl $reg for Register<$t> {
             $( field_method!($op, $name, $field, $type, $( $args ),* ); )*
+            $( field_present!(impl, $op, [$( $pname )?], $field, $( $args ),*); )*
+            $(
+                fn reset_value() -> $t { $reset }
+                fn reset(self) -> Self { self.set(<Self as $reg>::reset_value()) }
+            )?
+            register_debug!(impl, [$( $dbg )?], $reg, $t, [$( $op $name ),*]);
+        }
+    };
+    (@access [$( $dbg:tt )?], $reg:ident, $t:ty, ro, $( $rest:tt )*) => {
+        register!(@impl ro, [$( $dbg )?], $reg, $t, $( $rest )*);
+    };
+    (@access [$( $dbg:tt )?], $reg:ident, $t:ty, wo, $( $rest:tt )*) => {
+        register!(@impl wo, [$( $dbg )?], $reg, $t, $( $rest )*);
+    };
+    (@access [$( $dbg:tt )?], $reg:ident, $t:ty, rw, $( $rest:tt )*) => {
+        register!(@impl rw, [$( $dbg )?], $reg, $t, $( $rest )*);
+    };
+    (@access [$( $dbg:tt )?], $reg:ident, $t:ty, $( $rest:tt )*) => {
+        register!(@impl rw, [$( $dbg )?], $reg, $t, $( $rest )*);
+    };
+    ($reg:ident, $t:ty, debug, $( $rest:tt )*) => {
+        register!(@access [debug], $reg, $t, $( $rest )*);
+    };
+    ($reg:ident, $t:ty, $( $rest:tt )*) => {
+        register!(@access [], $reg, $t, $( $rest )*);
+    };
+}
+
+#[doc = "Creates a map of contiguous registers of the same type with automatically assigned addresses\n"]
+#[doc = "`register_map!(name = base, type, [reg, ...])` generates a type with a `BASE` address constant and an accessor"]
+#[doc = "per register, each offset by the type size from the previous. Reserved slots are written as a bare `_` and"]
+#[doc = "advance the offset without generating an accessor"]
+#[doc = "```"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "register_map!(Uart = 0x4000_0000, u32, [ctrl, status, data, _, config]);"]
+#[doc = "# fn main() { assert_eq!(0x4000_0010, Uart::config().addr()); }"]
+#[doc = "```\n"]
+#[doc = "Other names starting with `_` are rejected at compile time rather than generating a public accessor"]
+#[doc = "```compile_fail"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "register_map!(Uart = 0x4000_0000, u32, [ctrl, status, data, _reserved, config]);"]
+#[doc = "# fn main() {}"]
+#[doc = "```"]
+#[macro_export]
+macro_rules! register_map {
+    (@accessors $t:ty, $offset:expr, ) => {};
+    (@accessors $t:ty, $offset:expr, _ $(, $rest:tt )*) => {
+        register_map!(@accessors $t, $offset + 1, $( $rest ),*);
+    };
+    (@accessors $t:ty, $offset:expr, $reg:ident $(, $rest:tt )*) => {
+        #[doc = concat!("Returns the `", stringify!($reg), "` register")]
+        pub fn $reg() -> $crate::register::Register<$t> {
+            $crate::register::Register::new(Self::BASE + ($offset) * $crate::__core::mem::size_of::<$t>())
+        }
+        register_map!(@accessors $t, $offset + 1, $( $rest ),*);
+    };
+    ($name:ident = $base:expr, $t:ty, [ $( $reg:tt ),* $(,)? ]) => {
+        $( const _: () = assert!(stringify!($reg).len() == 1 || stringify!($reg).as_bytes()[0] != b'_',
+            concat!("register `", stringify!($reg), "` starts with `_`, use a bare `_` for reserved registers")); )*
+        pub struct $name;
+        impl $name {
+            #[doc = "Base address of the register map"]
+            pub const BASE: usize = $base;
+            register_map!(@accessors $t, 0, $( $reg ),*);
+        }
+    };
+}
+
+#[doc = "Composes a constant register value from `register!` field constants\n"]
+#[doc = "`register_value!(name, type, { FIELD: value, ... })` uses the `FieldSpec` constants emitted by `register!`,"]
+#[doc = "so may be used to initialise `const` values. Boolean values are converted to 0 or 1."]
+#[macro_export]
+macro_rules! register_value {
+    ($reg:ident, $t:ty, { $( $cname:ident : $val:expr ),* $(,)? }) => {
+        (0 $( | ((($val as $t) & <$crate::register::Register<$t> as $reg>::$cname.mask) << <$crate::register::Register<$t> as $reg>::$cname.shift) )*)
+    }
+}
+
+#[doc = "Creates an enum of named flags for the bits of a register\n"]
+#[doc = "`flags!(name, type, [FlagName = bit index, ...])` generates the enum and a `Flags` implementation,"]
+#[doc = "so the set flags of a register may be iterated with `reg.flags::<name>()`"]
+#[doc = "```"]
+#[doc = "# #[macro_use] extern crate embedded_builder;"]
+#[doc = "# use embedded_builder::register::Register;"]
+#[doc = "flags!(Irq, u16, [Rx = 0, Tx = 3]);"]
+#[doc = "# fn main() {"]
+#[doc = "let mut i = Register::<u16>(0, 1 << 3).flags::<Irq>();"]
+#[doc = "assert_eq!(Some(Irq::Tx), i.next());"]
+#[doc = "assert_eq!(None, i.next());"]
+#[doc = "# }"]
+#[doc = "```"]
+#[macro_export]
+macro_rules! flags {
+    ($name:ident, $t:ty, [ $( $flag:ident = $index:expr ),* $(,)? ]) => {
+        #[derive(Debug, PartialEq, Clone, Copy)]
+        pub enum $name {
+            $( $flag ),*
+        }
+        impl $crate::register::Flags<$t> for $name {
+            const FLAGS: &'static [($name, $t)] = &[ $( ($name::$flag, $index) ),* ];
         }
     }
 }
 
+#[doc = "Decodes a tuple of fields from a single register value\n"]
+#[doc = "The register expression is evaluated once, so `read_fields!(reg.read(), a, b)` performs a single volatile read"]
+#[doc = "and each field is decoded from that value via the `register!` generated getters"]
+#[macro_export]
+macro_rules! read_fields {
+    ($reg: expr, $( $name: ident ),+ ) => {{
+        let r = &$reg;
+        ( $( r.$name(), )+ )
+    }}
+}
+
 #[cfg(test)]
 mod tests {
-    use ::register::Register;
+    use ::register::{Register, FieldSpec, Scaled};
 
     register!(TESTREG1, u16, 
         [
@@ -93,21 +435,186 @@ mod tests {
         ]
     );
 
+    register!(TESTREG2, u16, reset = 0x8000,
+        [
+            r, read_bit15,  1,  bool,   15;
+        ]
+    );
+
+    #[test]
+    fn register_reset() {
+        let r = Register::<u16>(0, 0x0F0F);
+        assert_eq!(0x8000, <Register<u16> as TESTREG2>::reset_value());
+
+        let r = r.reset();
+        assert_eq!(0x8000, r.value());
+        assert_eq!(true, r.read_bit15());
+
+        let r = r.clear(0xFFFF).reset().set_bit(0, true);
+        assert_eq!(0x8001, r.value());
+    }
+
+    register!(TESTREG3, u16,
+        [
+            r, read_en,     1,  bool,   0 => EN;
+            r, read_div,    1,  u16,    4,  0b1111 => DIV;
+            w, write_div,   1,  u16,    4,  0b1111;
+        ]
+    );
+
+    register!(TESTREG4, u16, ro,
+        [
+            r, status_ready,    1,  bool,   0;
+            r, status_state,    1,  u16,    1,  0b11;
+        ]
+    );
+
+    register!(TESTREG5, u16, wo,
+        [
+            w, config_en,       1,  bool,   0;
+            w, config_div,      1,  u16,    1,  0b11;
+        ]
+    );
+
+    register!(TESTREG6, u16, debug,
+        [
+            r, en,      1,  bool,   0;
+            r, div,     1,  u16,    4,  0b1111;
+            w, set_div, 1,  u16,    4,  0b1111;
+        ]
+    );
+
+    #[test]
+    fn register_debug() {
+        let r = Register::<u16>(0, 0x0001).set_div(3);
+        let s = ::std::format!("{:?}", r.debug_fields());
+        assert_eq!("TESTREG6 { en: true, div: 3 }", s);
+        assert_eq!(true, r.en());
+        assert_eq!(3, r.div());
+    }
+
+    #[test]
+    fn register_access() {
+        let r = Register::<u16>(0, 0b101);
+        assert_eq!(true, r.status_ready());
+        assert_eq!(0b10, r.status_state());
+
+        let r = r.clear(0xFFFF).config_en(true).config_div(0b11);
+        assert_eq!(0b111, r.value());
+        assert_eq!(0b110, r.config_en(false).value());
+    }
+
+    #[test]
+    fn register_field_consts() {
+        assert_eq!(FieldSpec{ shift: 0, mask: 1 }, <Register<u16> as TESTREG3>::EN);
+        assert_eq!(FieldSpec{ shift: 4, mask: 0b1111 }, <Register<u16> as TESTREG3>::DIV);
+
+        let div = <Register<u16> as TESTREG3>::DIV;
+        let r = Register::<u16>(0, 0).set_masked(div.shift, div.mask, 3);
+        assert_eq!(3 << 4, r.value());
+        assert_eq!(3, r.read_div());
+        assert_eq!(false, r.read_en());
+        assert_eq!(5 << 4, r.write_div(5).value());
+    }
+
+    #[test]
+    fn register_value() {
+        const INIT: u16 = register_value!(TESTREG3, u16, { EN: true, DIV: 3 });
+        assert_eq!(1 << 0 | 3 << 4, INIT);
+        assert_eq!(0x0000, register_value!(TESTREG3, u16, {}));
+        assert_eq!(0x00F0, register_value!(TESTREG3, u16, { EN: false, DIV: 0xFF, }));
+    }
+
+    flags!(TestIrq, u16, [
+        Rx = 0,
+        Tx = 3,
+        Error = 7,
+    ]);
+
+    #[test]
+    fn register_flags() {
+        let r = Register::<u16>(0, 1 << 0 | 1 << 7 | 1 << 9);
+        let mut i = r.flags::<TestIrq>();
+        assert_eq!(Some(TestIrq::Rx), i.next());
+        assert_eq!(Some(TestIrq::Error), i.next());
+        assert_eq!(None, i.next());
+
+        assert_eq!(Some(TestIrq::Tx), Register::<u16>(0, 1 << 3).flags::<TestIrq>().next());
+    }
+
+    #[test]
+    fn register_read_fields() {
+        let r = Register::<u16>(0, 1 << 1 | 5 << 2);
+        let (bit, var) = read_fields!(r, read_bit1, read_var1);
+        assert_eq!(true, bit);
+        assert_eq!(5, var);
+    }
+
     #[test]
     fn register_traits() {
         let mut r = Register::<u16>(0, 0);
 
         assert_eq!(0, r.value());
         assert_eq!(false, r.read_bit1());
-        r.write_bit1(true);
+        r = r.write_bit1(true);
         assert_eq!(true, r.read_bit1());
         assert_eq!(1 << 1, r.value());
 
         assert_eq!(0, TESTREG1::read_var1(&r));
-        TESTREG1::write_var1(&mut r, 3);
+        r = TESTREG1::write_var1(r, 3);
         assert_eq!(3, TESTREG1::read_var1(&r));
 
         assert_eq!(1 << 1 | 3 << 2, r.value());
     }
-}
 
+    register_map!(TestMap = 0x4000_0000, u32, [ctrl, status, data, _, config]);
+
+    #[test]
+    fn register_map() {
+        assert_eq!(0x4000_0000, TestMap::ctrl().addr());
+        assert_eq!(0x4000_0004, TestMap::status().addr());
+        assert_eq!(0x4000_0008, TestMap::data().addr());
+        assert_eq!(TestMap::BASE + 16, TestMap::config().addr());
+    }
+
+    register!(TESTREG7, u16, ro, [
+        r, channel, 1, u16, 4, 0b111 => ? channel_present;
+        r, mode, 1, u16, 8, 0b11 => MODE => ? mode_present;
+        r, enabled, 1, u16, 0;
+    ]);
+
+    #[test]
+    fn field_present() {
+        let r = Register::<u16>(0, 0b1000_1111);
+        assert_eq!(0, r.channel());
+        assert_eq!(false, r.channel_present());
+        assert_eq!(true, r.enabled());
+
+        let r = Register::<u16>(0, 0b0100_0000);
+        assert_eq!(0b100, r.channel());
+        assert_eq!(true, r.channel_present());
+        assert_eq!(false, r.enabled());
+
+        assert_eq!(8, <Register<u16> as TESTREG7>::MODE.shift);
+        assert_eq!(false, r.mode_present());
+        let r = Register::<u16>(0, 0b10 << 8);
+        assert_eq!(0b10, r.mode());
+        assert_eq!(true, r.mode_present());
+    }
+
+    register!(TESTREG8, u32, width = 32, [
+        r, div, 1, u32, 4, 0xF;
+        rs, freq, 1, u32, 4, 0xF;
+        ws, set_freq, 1, u32, 4, 0xF;
+    ]);
+
+    #[test]
+    fn scaled_fields() {
+        let r = Register::<u32>(0, 3 << 4);
+        assert_eq!(Scaled(3_000_000), r.freq(1_000_000));
+
+        let r = r.set_freq(Scaled(5_000_000), 1_000_000);
+        assert_eq!(5, r.div());
+        assert_eq!(5 << 4, r.value());
+    }
+}
@@ -1,9 +1,24 @@
 // embedded-builder helpers for building embedded hardware interfaces
 // Copyright 2018 Ryan Kurte
 
-#![feature(used)]
-
 #![no_std]
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
+
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
+// Re-exported so exported macros can name core paths in callers on any edition
+#[doc(hidden)]
+pub extern crate core as __core;
+
+#[cfg(feature = "critical-section")]
+extern crate critical_section;
+
+#[cfg(feature = "serde")]
+extern crate serde;
+
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 #[doc = "Helpers provide macros for the generation of accessors over Register objects"]
 #[macro_use]
@@ -13,6 +28,34 @@ pub mod helpers;
 #[macro_use]
 pub mod region;
 
+#[doc = "Cell provides a typed volatile cell for plain memory-mapped access"]
+pub mod cell;
+
 #[doc = "Register provides a register type with chained building and modification"]
 #[macro_use]
 pub mod register;
+
+#[doc = "State provides a register wrapper tracking whether the internal value was read or built from scratch"]
+pub mod state;
+
+#[doc = "Register24 provides a register type for packed 24-bit registers"]
+pub mod register24;
+
+#[doc = "Bank provides types for indexed access to contiguous banks of registers"]
+pub mod bank;
+
+#[doc = "Transaction provides a builder for ordered multi-register writes"]
+pub mod transaction;
+
+#[doc = "Access provides an abstraction over register reads and writes for non memory-mapped register files"]
+pub mod access;
+
+#[doc = "Lockable provides a register type for registers guarded by an unlock sequence"]
+pub mod lockable;
+
+#[doc = "Guard provides a scoped register guard that writes modifications back when dropped"]
+pub mod guard;
+
+#[doc = "Test support provides helpers for verifying driver register access sequences in host tests"]
+#[cfg(feature = "test-support")]
+pub mod test_support;
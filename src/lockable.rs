@@ -0,0 +1,157 @@
+// Lockable register type for registers guarded by an unlock sequence
+// Copyright 2018 Ryan Kurte
+
+use ::access::{RegisterAccess, Mmio};
+use ::register::{Register, RegisterType};
+
+#[doc = "RegError describes failures writing to guarded registers"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RegError {
+    #[doc = "The value read back after a write did not match the value written"]
+    Verify,
+}
+
+// Lockable register helper structure
+// This wraps a register with the unlock register address and key required to modify it.
+#[doc = "LockableRegister type wraps a register that must be unlocked by writing a key prior to modification"]
+#[derive(Debug, PartialEq, Clone)]
+pub struct LockableRegister<T: RegisterType<T>> {
+    reg: Register<T>,
+    unlock_addr: usize,
+    key: T,
+}
+
+impl <T: RegisterType<T>>LockableRegister<T> {
+    #[doc = "Creates a new lockable register unlocked by writing `key` to `unlock_addr`"]
+    pub fn new(reg: Register<T>, unlock_addr: usize, key: T) -> LockableRegister<T> {
+        LockableRegister{ reg, unlock_addr, key }
+    }
+
+    #[doc = "Returns the wrapped register"]
+    pub fn register(&self) -> &Register<T> {
+        &self.reg
+    }
+
+    #[doc = "Applies the provided function to the wrapped register value"]
+    pub fn update<F: FnOnce(Register<T>) -> Register<T>>(mut self, f: F) -> LockableRegister<T> {
+        self.reg = f(self.reg);
+        self
+    }
+
+    #[doc = "Writes the unlock key to the unlock register"]
+    pub fn unlock(&self) {
+        self.unlock_with(&mut Mmio)
+    }
+
+    #[doc = "Writes the unlock key to the unlock register using the provided accessor"]
+    pub fn unlock_with(&self, access: &mut impl RegisterAccess<T>) {
+        access.write(self.unlock_addr, self.key)
+    }
+
+    #[doc = "Writes the internal value to the register and reads it back to verify the write was applied"]
+    #[doc = "Returns `RegError::Verify` if the read back value differs, for example if the register is locked"]
+    pub fn write(&self) -> Result<(), RegError> {
+        self.write_with(&mut Mmio)
+    }
+
+    #[doc = "Writes and verifies the internal value as with `write`, using the provided accessor"]
+    pub fn write_with(&self, access: &mut impl RegisterAccess<T>) -> Result<(), RegError> {
+        access.write(self.reg.addr(), self.reg.value());
+        match self.reg.read_with(access).value() == self.reg.value() {
+            true => Ok(()),
+            false => Err(RegError::Verify),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::access::RegisterAccess;
+    use ::lockable::{LockableRegister, RegError};
+    use ::register::Register;
+
+    const UNLOCK: usize = 0x00;
+    const KEY: u32 = 0xC0DE;
+
+    // Fake register file that ignores writes until the key is written to the unlock address
+    struct Fake {
+        mem: [u32; 16],
+        unlocked: bool,
+    }
+
+    impl RegisterAccess<u32> for Fake {
+        fn read(&self, addr: usize) -> u32 {
+            self.mem[addr / 4]
+        }
+
+        fn write(&mut self, addr: usize, v: u32) {
+            match (addr, v) {
+                (UNLOCK, KEY) => self.unlocked = true,
+                (UNLOCK, _) => (),
+                _ if self.unlocked => self.mem[addr / 4] = v,
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn locked() {
+        let mut f = Fake{ mem: [0; 16], unlocked: false };
+        let r = LockableRegister::new(Register::<u32>::new(0x04), UNLOCK, KEY)
+            .update(|r| r.set(0x1234));
+
+        assert_eq!(Err(RegError::Verify), r.write_with(&mut f));
+        assert_eq!(0, f.mem[1]);
+    }
+
+    #[test]
+    fn unlocked() {
+        let mut f = Fake{ mem: [0; 16], unlocked: false };
+        let r = LockableRegister::new(Register::<u32>::new(0x04), UNLOCK, KEY)
+            .update(|r| r.set(0x1234));
+
+        r.unlock_with(&mut f);
+        assert_eq!(Ok(()), r.write_with(&mut f));
+        assert_eq!(0x1234, f.mem[1]);
+    }
+
+    #[test]
+    fn mmio() {
+        let mut mem = [0u32; 2];
+        let base = mem.as_mut_ptr() as usize;
+        let r = LockableRegister::new(Register::<u32>::new(base + 4), base, KEY)
+            .update(|r| r.set(0x5678));
+
+        r.unlock();
+        assert_eq!(Ok(()), r.write());
+        assert_eq!([KEY, 0x5678], mem);
+        assert_eq!(0x5678, r.register().value());
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn mmio_trace() {
+        use std::sync::Mutex;
+        use std::vec::Vec;
+        use ::register::{set_trace_hook, TRACE_TEST_LOCK};
+
+        static WRITES: Mutex<Vec<(usize, u64)>> = Mutex::new(Vec::new());
+        fn record(addr: usize, value: u64) {
+            WRITES.lock().unwrap().push((addr, value));
+        }
+
+        let mut mem = [0u32; 2];
+        let base = mem.as_mut_ptr() as usize;
+        let r = LockableRegister::new(Register::<u32>::new(base + 4), base, KEY)
+            .update(|r| r.set(0x5678));
+
+        let _lock = TRACE_TEST_LOCK.lock().unwrap();
+        set_trace_hook(Some(record));
+        r.unlock();
+        assert_eq!(Ok(()), r.write());
+        set_trace_hook(None);
+
+        assert_eq!(std::vec![(base, KEY as u64), (base + 4, 0x5678)], *WRITES.lock().unwrap());
+        assert_eq!([KEY, 0x5678], mem);
+    }
+}
@@ -1,15 +1,46 @@
 // Region type for type-safe memory mapping
 // Copyright 2018 Ryan Kurte
 
-use core::slice;
+use core::{iter, mem, slice};
+use core::convert::TryFrom;
+use core::ops::Range;
 use core::ptr::{read_volatile, write_volatile};
 
+use ::cell::{VolatileCell, VolatileRef};
+use ::register::RegisterType;
+
 // Region helper wraps regions of a given type in volatile read and writes
 #[doc = "Region type describes a memory region containing an array of objects"]
 #[doc = "This can be used to memory map lists of objects, for example, pixels in a framebuffer"]
 #[derive(Debug, PartialEq)]
 pub struct Region<T: 'static> (&'static mut[T]);
 
+#[doc = "RegionError describes reasons a region could not be created"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RegionError {
+    #[doc = "Region address is null"]
+    Null,
+    #[doc = "Region length is zero"]
+    ZeroLength,
+    #[doc = "Region address is not aligned to the object type"]
+    Misaligned,
+    #[doc = "Region size overflows the address space or exceeds `isize::MAX` bytes"]
+    Overflow,
+}
+
+#[doc = "Create a region of `len` objects of the provided type starting at the address of a linker symbol"]
+#[doc = "This avoids hard-coding addresses decided by the linker, ie. `region_from_symbol!(_framebuffer, u16, 320 * 240)`"]
+#[doc = ""]
+#[doc = "# Safety"]
+#[doc = "The symbol must reference memory valid for reads and writes of `len` objects of the provided type,"]
+#[doc = "suitably aligned, that is not accessed through any other region while the returned region is in use"]
+#[macro_export]
+macro_rules! region_from_symbol {
+    ($sym: path, $t: ty, $len: expr) => {
+        $crate::region::Region::<$t>::new(&raw const $sym as usize, $len)
+    };
+}
+
 #[doc = "From implementation creates regions from tuples of (address: usize, size: usize)"]
 impl <T>From<(usize, usize)> for Region<T> {
     fn from(v: (usize, usize)) -> Region<T> {
@@ -17,19 +48,34 @@ impl <T>From<(usize, usize)> for Region<T> {
     }
 }
 
+#[doc = "PartialEq implementation compares the objects in a region against a slice, see `eq_slice`"]
+impl <T: Copy + PartialEq>PartialEq<[T]> for Region<T> {
+    fn eq(&self, other: &[T]) -> bool {
+        self.eq_slice(other)
+    }
+}
+
+#[doc = "IntoIterator implementation consumes a region, yielding each object in order using volatile reads"]
+#[doc = "Note that the underlying memory is not freed, as regions describe memory-mapped or static memory"]
+impl <T: Copy>IntoIterator for Region<T> {
+    type Item = T;
+    type IntoIter = iter::Map<slice::Iter<'static, T>, fn(&T) -> T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let data: &'static [T] = self.0;
+        data.iter().map(|e| unsafe { read_volatile(e as *const T) })
+    }
+}
+
 // Generic region implementation
 impl <T>Region<T> {
     #[doc = "Read an object from the provided (absolute) address"]
     pub fn read_addr(addr: u32) -> T {
-        unsafe {
-            read_volatile(addr as *const T)
-        }
+        VolatileCell::new(addr as usize).get()
     }
     #[doc = "Write an object to the provided (absolute) address"]
     pub fn write_addr(addr: u32, v: T) {
-        unsafe {
-            write_volatile(addr as *mut T, v)
-        }
+        VolatileCell::new(addr as usize).set(v)
     }
 
     #[doc = "Create a new indexable memory region of the provided type"]
@@ -39,12 +85,851 @@ impl <T>Region<T> {
             Region::<T>(data)
         }
     }
+    #[doc = "Create a new indexable memory region of the provided type, validating the address and length"]
+    #[doc = "This rejects null or misaligned addresses, zero lengths, and regions that overflow the address space or exceed `isize::MAX` bytes"]
+    pub fn try_new(addr: usize, len: usize) -> Result<Region<T>, RegionError> {
+        if addr == 0 {
+            return Err(RegionError::Null);
+        }
+        if len == 0 {
+            return Err(RegionError::ZeroLength);
+        }
+        if addr % mem::align_of::<T>() != 0 {
+            return Err(RegionError::Misaligned);
+        }
+        // Slices are limited to isize::MAX bytes, as well as the end address fitting in usize
+        match len.checked_mul(mem::size_of::<T>()).filter(|n| *n <= isize::MAX as usize).and_then(|n| addr.checked_add(n)) {
+            Some(_) => Ok(Region::new(addr, len)),
+            None => Err(RegionError::Overflow),
+        }
+    }
+
+    #[doc = "Create a new region wrapping an existing static slice"]
+    #[doc = "This is useful for regions backed by `static mut` buffers, such as DMA buffers"]
+    pub fn from_slice(slice: &'static mut [T]) -> Region<T> {
+        Region::<T>(slice)
+    }
+
+    #[doc = "Fetch a raw pointer to the start of the region"]
+    pub fn as_ptr(&self) -> *const T {
+        self.0.as_ptr()
+    }
+
+    #[doc = "Fetch a mutable raw pointer to the start of the region"]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.0.as_mut_ptr()
+    }
+
+    #[doc = "Fetch the base address of the region"]
+    pub fn addr(&self) -> usize {
+        self.0.as_ptr() as usize
+    }
+
+    #[doc = "Create a new region wrapping an existing static array"]
+    pub fn from_array<const N: usize>(arr: &'static mut [T; N]) -> Region<T> {
+        Region::<T>(&mut arr[..])
+    }
+
+    #[doc = "View the region as a fixed size array, if the region length matches"]
+    pub fn as_array<const N: usize>(&self) -> Option<&[T; N]> {
+        <&[T; N]>::try_from(&self.0[..]).ok()
+    }
+
+    #[doc = "Fetch the number of objects in the region"]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[doc = "Check whether the region contains no objects"]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     #[doc = "Read an object from the provided index"]
+    #[doc = "Note this returns a plain reference rather than performing a volatile read,"]
+    #[doc = "use `get_volatile` for memory-mapped regions where reads must not be elided"]
     pub fn read_index(&self, i: usize) -> &T {
         &self.0[i]
     }
-    #[doc = "Write an object to the provided index"]
+    #[doc = "Read an object from the provided index using a volatile read"]
+    pub fn get_volatile(&self, i: usize) -> T where T: Copy {
+        unsafe {
+            read_volatile(&self.0[i] as *const T)
+        }
+    }
+
+    #[doc = "Write an object to the provided index using a volatile write"]
+    pub fn set_volatile(&mut self, i: usize, v: T) {
+        unsafe {
+            write_volatile(&mut self.0[i] as *mut T, v)
+        }
+    }
+
+    #[doc = "Swap the object at the provided index, returning the previous object"]
+    #[doc = "The previous object is fetched with a volatile read before the new object is written with a volatile write,"]
+    #[doc = "note the pair is not atomic. Panics if the index is out of bounds"]
+    pub fn swap_value(&mut self, i: usize, v: T) -> T where T: Copy {
+        let old = self.get_volatile(i);
+        self.set_volatile(i, v);
+        old
+    }
+
+    #[doc = "Write an object to the provided index using a volatile write"]
+    #[doc = "Note this previously performed a plain store, which could be elided or reordered"]
     pub fn write_index(&mut self, i: usize, v: T) {
-        self.0[i] = v;
+        self.set_volatile(i, v)
+    }
+
+    #[doc = "Read an object of another type from the provided byte offset using a volatile read"]
+    #[doc = "This is useful for `#[repr(C)]` structs packed into mixed-width peripheral blocks,"]
+    #[doc = "panics if the object extends past the end of the region or is misaligned"]
+    pub fn read_struct<U: Copy>(&self, byte_offset: usize) -> U {
+        let addr = self.struct_addr::<U>(byte_offset);
+        unsafe {
+            read_volatile(addr as *const U)
+        }
+    }
+
+    #[doc = "Write an object of another type to the provided byte offset using a volatile write"]
+    #[doc = "Panics if the object extends past the end of the region or is misaligned"]
+    pub fn write_struct<U: Copy>(&mut self, byte_offset: usize, v: U) {
+        let addr = self.struct_addr::<U>(byte_offset);
+        unsafe {
+            write_volatile(addr as *mut U, v)
+        }
+    }
+
+    // Computes and checks the address of an object of type U at the provided byte offset
+    fn struct_addr<U>(&self, byte_offset: usize) -> usize {
+        let size = mem::size_of_val(self.0);
+        assert!(byte_offset <= size && mem::size_of::<U>() <= size - byte_offset, "struct out of bounds");
+        let addr = self.addr() + byte_offset;
+        assert!(addr % mem::align_of::<U>() == 0, "struct is misaligned");
+        addr
+    }
+
+    #[doc = "Create a new region over `len` objects starting at index `start` of this region"]
+    #[doc = "Panics if the window extends past the end of this region"]
+    #[doc = "Note that the returned region aliases this one, so care must be taken not to hold"]
+    #[doc = "overlapping references through both regions at the same time"]
+    pub fn window(&mut self, start: usize, len: usize) -> Region<T> {
+        assert!(start <= self.0.len() && len <= self.0.len() - start, "window out of bounds");
+        Region::new(self.addr() + start * mem::size_of::<T>(), len)
+    }
+
+    #[doc = "Create a new region over `len` objects starting at index `start` of this region, as with `window`"]
+    #[doc = "Returns `None` rather than panicking if the window extends past the end of this region"]
+    #[doc = "or the address computation overflows, for use with untrusted dimensions"]
+    pub fn try_window(&mut self, start: usize, len: usize) -> Option<Region<T>> {
+        if start.checked_add(len)? > self.0.len() {
+            return None;
+        }
+        let addr = start.checked_mul(mem::size_of::<T>()).and_then(|o| self.addr().checked_add(o))?;
+        Some(Region::new(addr, len))
+    }
+
+    #[doc = "Iterate over the objects in the region"]
+    #[doc = "This returns a standard slice iterator so may be reversed with `rev()`, note that reads are not volatile"]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.0.iter()
+    }
+
+    #[doc = "Iterate mutably over the objects in the region"]
+    #[doc = "This returns a standard slice iterator so may be reversed with `rev()`, note that writes are not volatile"]
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.0.iter_mut()
+    }
+
+    #[doc = "Iterate over volatile proxies for the objects in the region"]
+    #[doc = "Unlike `iter_mut` each proxy performs volatile reads and writes, preserving MMIO semantics"]
+    pub fn iter_volatile_mut(&mut self) -> impl Iterator<Item = VolatileRef<'_, T>> {
+        self.0.iter_mut().map(VolatileRef::new)
+    }
+
+    #[doc = "Fetch the first object in the region, or `None` if the region is empty"]
+    pub fn first(&self) -> Option<&T> {
+        self.0.first()
+    }
+
+    #[doc = "Fetch the last object in the region, or `None` if the region is empty"]
+    pub fn last(&self) -> Option<&T> {
+        self.0.last()
+    }
+
+    #[doc = "Iterate over (absolute address, object) pairs in the region"]
+    pub fn iter_addr(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+        let base = self.addr();
+        self.0.iter().enumerate().map(move |(i, v)| (base + i * mem::size_of::<T>(), v))
+    }
+
+    #[doc = "Write the default value to every object in the region using volatile writes"]
+    pub fn clear(&mut self) where T: Default + Copy {
+        let v = T::default();
+        for e in self.0.iter_mut() {
+            unsafe {
+                write_volatile(e as *mut T, v);
+            }
+        }
+    }
+
+    #[doc = "Write `f(index)` to every object in the region using volatile writes"]
+    #[doc = "This supports address-dependent patterns, ie. `r.fill_with(|i| 0x5555 ^ i as u16)` for memory tests"]
+    pub fn fill_with<F: FnMut(usize) -> T>(&mut self, mut f: F) {
+        for (i, e) in self.0.iter_mut().enumerate() {
+            unsafe {
+                write_volatile(e as *mut T, f(i));
+            }
+        }
+    }
+
+    #[doc = "Check whether every object in the region equals the provided value, reading each object volatilely"]
+    pub fn is_all(&self, v: &T) -> bool where T: Copy + PartialEq {
+        self.position(|e| e != v).is_none()
+    }
+
+    #[doc = "Find the index of the first object matching the provided predicate, reading each object volatilely"]
+    pub fn position<P: FnMut(&T) -> bool>(&self, mut pred: P) -> Option<usize> where T: Copy {
+        self.0.iter().position(|e| {
+            let v = unsafe { read_volatile(e as *const T) };
+            pred(&v)
+        })
+    }
+
+    #[doc = "Check whether the region contains the provided object, reading each object volatilely"]
+    pub fn contains(&self, v: &T) -> bool where T: Copy + PartialEq {
+        self.position(|e| e == v).is_some()
+    }
+
+    #[doc = "Check whether the region matches the provided slice, reading each object volatilely"]
+    #[doc = "Regions and slices of different lengths are never equal"]
+    pub fn eq_slice(&self, other: &[T]) -> bool where T: Copy + PartialEq {
+        self.len() == other.len() && self.0.iter().zip(other.iter()).all(|(e, o)| {
+            let v = unsafe { read_volatile(e as *const T) };
+            v == *o
+        })
+    }
+
+    #[doc = "Copy objects from this region into the destination region using volatile reads and writes"]
+    #[doc = "If the regions differ in length only the shorter prefix is copied, the number of objects copied is returned"]
+    pub fn clone_to(&self, dst: &mut Region<T>) -> usize where T: Copy {
+        let n = self.len().min(dst.len());
+        for (s, d) in self.0[..n].iter().zip(dst.0[..n].iter_mut()) {
+            unsafe {
+                write_volatile(d as *mut T, read_volatile(s as *const T));
+            }
+        }
+        n
+    }
+
+    #[doc = "Copy `rows` lines of `src_width` objects from the source region into this region, stepping by"]
+    #[doc = "`src_width` objects per line in the source and `dst_width` objects per line in this region,"]
+    #[doc = "as when drawing a sprite into a framebuffer. Panics if either region is too small"]
+    pub fn blit(&mut self, src: &Region<T>, src_width: usize, dst_width: usize, rows: usize) where T: Copy {
+        assert!(src_width <= dst_width, "blit source wider than destination");
+        let src_len = src_width.checked_mul(rows).expect("blit out of bounds");
+        let dst_len = match rows {
+            0 => 0,
+            _ => dst_width.checked_mul(rows - 1).and_then(|n| n.checked_add(src_width)).expect("blit out of bounds"),
+        };
+        assert!(src_len <= src.len() && dst_len <= self.len(), "blit out of bounds");
+        for y in 0..rows {
+            let s = &src.0[y * src_width..][..src_width];
+            let d = &mut self.0[y * dst_width..][..src_width];
+            for (s, d) in s.iter().zip(d.iter_mut()) {
+                unsafe {
+                    write_volatile(d as *mut T, read_volatile(s as *const T));
+                }
+            }
+        }
+    }
+
+    #[doc = "Copy the objects in the source range to the destination index within this region, as with `slice::copy_within`"]
+    #[doc = "Overlapping ranges are handled correctly, as when scrolling a framebuffer, and each object is copied using"]
+    #[doc = "volatile reads and writes. Panics if the source range or destination extent is out of bounds"]
+    pub fn copy_within(&mut self, src: Range<usize>, dest: usize) where T: Copy {
+        assert!(src.start <= src.end && src.end <= self.len(), "copy source out of bounds");
+        let n = src.end - src.start;
+        assert!(dest <= self.len() && n <= self.len() - dest, "copy destination out of bounds");
+        let p = self.as_mut_ptr();
+        let copy = |i: usize| unsafe {
+            write_volatile(p.add(dest + i), read_volatile(p.add(src.start + i)));
+        };
+        match dest <= src.start {
+            true => (0..n).for_each(copy),
+            false => (0..n).rev().for_each(copy),
+        }
+    }
+
+    #[doc = "Split the region into two non-overlapping regions at the provided index"]
+    #[doc = "The first region contains indices `[0, mid)` and the second `[mid, len)`, panics if `mid > len`"]
+    pub fn split_at_mut(self, mid: usize) -> (Region<T>, Region<T>) {
+        assert!(mid <= self.len(), "split index out of bounds");
+        let (a, b) = self.0.split_at_mut(mid);
+        (Region::<T>(a), Region::<T>(b))
+    }
+
+    #[doc = "Iterate over (row index, row) pairs of `width` objects, as in a framebuffer"]
+    #[doc = "If the region length is not a multiple of `width` the final row is shorter, panics if `width` is zero"]
+    pub fn rows(&self, width: usize) -> impl Iterator<Item = (usize, &[T])> + '_ {
+        self.0.chunks(width).enumerate()
+    }
+
+    #[doc = "Iterate over (row index, mutable row) pairs of `width` objects, as in a framebuffer"]
+    #[doc = "If the region length is not a multiple of `width` the final row is shorter, panics if `width` is zero"]
+    pub fn rows_mut(&mut self, width: usize) -> impl Iterator<Item = (usize, &mut [T])> + '_ {
+        self.0.chunks_mut(width).enumerate()
+    }
+
+    #[doc = "Apply a function to each object in the region using volatile reads and writes"]
+    pub fn map_in_place<F: Fn(T) -> T>(&mut self, f: F) where T: Copy {
+        for e in self.0.iter_mut() {
+            unsafe {
+                let v = read_volatile(e as *const T);
+                write_volatile(e as *mut T, f(v));
+            }
+        }
+    }
+}
+
+// Memory test implementation
+impl <T: Copy + PartialEq>Region<T> {
+    #[doc = "Write the pattern to every object in the region, then read each back using volatile accesses"]
+    #[doc = "Returns `Err(index)` for the first object that does not read back the pattern, for validating memory at startup"]
+    pub fn memtest(&mut self, pattern: T) -> Result<(), usize> {
+        let p = self.as_mut_ptr();
+        memtest_by(self.len(), pattern,
+            |i, v| unsafe { write_volatile(p.add(i), v) },
+            |i| unsafe { read_volatile(p.add(i)) })
+    }
+
+    #[doc = "Run `memtest` with a walking ones pattern, testing each bit of every object in turn"]
+    #[doc = "Returns `Err(index)` for the first object that fails any pattern"]
+    pub fn memtest_walking(&mut self) -> Result<(), usize> where T: RegisterType<T> {
+        for b in 0..T::bit_width() {
+            self.memtest(T::one() << T::truncate(b as u64))?;
+        }
+        Ok(())
+    }
+}
+
+// Writes the pattern to each of `len` objects, then reads each back, returning the index of the first mismatch
+fn memtest_by<T: Copy + PartialEq, W: FnMut(usize, T), R: FnMut(usize) -> T>(len: usize, pattern: T, mut write: W, mut read: R) -> Result<(), usize> {
+    for i in 0..len {
+        write(i, pattern);
+    }
+    match (0..len).find(|&i| read(i) != pattern) {
+        Some(i) => Err(i),
+        None => Ok(()),
+    }
+}
+
+// Byte region implementation
+impl Region<u8> {
+    #[doc = "Reinterpret a byte region as a region of a wider type, recomputing the length"]
+    #[doc = "Panics if `U` is zero-sized, the region is misaligned for `U` or its length is not a multiple of `U`'s size"]
+    #[doc = ""]
+    #[doc = "# Safety"]
+    #[doc = "The underlying memory must be valid for reads and writes as `U`, and must not be accessed"]
+    #[doc = "through any other region while the returned region is in use"]
+    pub unsafe fn reinterpret<U>(self) -> Region<U> {
+        let addr = self.0.as_ptr() as usize;
+        let len = self.0.len();
+        assert!(mem::size_of::<U>() != 0, "cannot reinterpret a region as a zero-sized type");
+        assert!(addr % mem::align_of::<U>() == 0, "region is misaligned for target type");
+        assert!(len % mem::size_of::<U>() == 0, "region length is not a multiple of target type size");
+        Region::new(addr, len / mem::size_of::<U>())
+    }
+
+    #[doc = "Read a single bit of a packed bit region, as in a 1bpp framebuffer"]
+    #[doc = "Bit `i` is located at bit `i % 8` (LSB first) of byte `i / 8`, panics if the byte is out of bounds"]
+    pub fn get_pixel(&self, i: usize) -> bool {
+        self.get_volatile(i / 8) & (1 << (i % 8)) != 0
+    }
+
+    #[doc = "Write a single bit of a packed bit region using a volatile read-modify-write of the containing byte"]
+    #[doc = "Bit `i` is located at bit `i % 8` (LSB first) of byte `i / 8`, panics if the byte is out of bounds"]
+    pub fn set_pixel(&mut self, i: usize, v: bool) {
+        let b = self.get_volatile(i / 8);
+        let b = match v {
+            true => b | (1 << (i % 8)),
+            false => b & !(1 << (i % 8)),
+        };
+        self.set_volatile(i / 8, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use ::region::{Region, RegionError, memtest_by};
+
+    #[test]
+    fn try_new() {
+        let mut mem = [0u32; 4];
+        let addr = mem.as_mut_ptr() as usize;
+
+        let r = Region::<u32>::try_new(addr, 4).unwrap();
+        assert_eq!(addr, r.addr());
+
+        assert_eq!(Err(RegionError::Null), Region::<u32>::try_new(0, 4));
+        assert_eq!(Err(RegionError::ZeroLength), Region::<u32>::try_new(addr, 0));
+        assert_eq!(Err(RegionError::Misaligned), Region::<u32>::try_new(addr + 2, 1));
+        assert_eq!(Err(RegionError::Overflow), Region::<u32>::try_new(addr, usize::MAX / 2));
+        assert_eq!(Err(RegionError::Overflow), Region::<u8>::try_new(0x1000, usize::MAX / 2 + 10));
+    }
+
+    #[test]
+    fn from_slice() {
+        static mut BUFF: [u16; 4] = [1, 2, 3, 4];
+        let mut r = Region::from_slice(unsafe { &mut *::core::ptr::addr_of_mut!(BUFF) });
+        assert_eq!(3, *r.read_index(2));
+        r.write_index(2, 10);
+        assert_eq!(10, *r.read_index(2));
+    }
+
+    #[test]
+    fn region_from_symbol() {
+        static mut SYMBOL: [u32; 4] = [1, 2, 3, 4];
+        let mut r = region_from_symbol!(SYMBOL, u32, 4);
+        assert_eq!(::core::ptr::addr_of!(SYMBOL) as usize, r.addr());
+        assert_eq!(4, r.len());
+        assert_eq!(3, r.get_volatile(2));
+        r.set_volatile(0, 10);
+        assert_eq!(10, unsafe { SYMBOL[0] });
+    }
+
+    #[test]
+    fn pointers() {
+        let mut mem = [0u32; 4];
+        let addr = mem.as_mut_ptr() as usize;
+        let mut r = Region::<u32>::new(addr, 4);
+        assert_eq!(addr, r.as_ptr() as usize);
+        assert_eq!(addr, r.as_mut_ptr() as usize);
+        assert_eq!(addr, r.addr());
+    }
+
+    #[test]
+    fn window() {
+        let mut mem = [0u16; 16];
+        let mut r = Region::<u16>::new(mem.as_mut_ptr() as usize, 16);
+        let mut w = r.window(4, 4);
+        assert_eq!(r.addr() + 8, w.addr());
+
+        w.write_index(0, 0xAA);
+        w.write_index(3, 0xBB);
+        assert_eq!(0xAA, *r.read_index(4));
+        assert_eq!(0xBB, *r.read_index(7));
+        assert_eq!(0, *r.read_index(8));
+    }
+
+    #[test]
+    fn try_window() {
+        let mut mem = [0u16; 16];
+        let mut r = Region::<u16>::new(mem.as_mut_ptr() as usize, 16);
+        let w = r.try_window(4, 12).unwrap();
+        assert_eq!(r.addr() + 8, w.addr());
+        assert_eq!(12, w.len());
+
+        assert_eq!(None, r.try_window(4, 13));
+        assert_eq!(None, r.try_window(16, 1));
+        assert_eq!(None, r.try_window(usize::MAX, 2));
+        assert_eq!(None, r.try_window(2, usize::MAX));
+    }
+
+    #[test]
+    #[should_panic(expected = "window out of bounds")]
+    fn window_out_of_bounds() {
+        let mut mem = [0u16; 16];
+        let mut r = Region::<u16>::new(mem.as_mut_ptr() as usize, 16);
+        r.window(12, 5);
+    }
+
+    #[test]
+    fn iter_addr() {
+        let mut mem = [10u32, 11, 12, 13];
+        let base = mem.as_mut_ptr() as usize;
+        let r = Region::<u32>::new(base, 4);
+        for (i, (addr, v)) in r.iter_addr().enumerate() {
+            assert_eq!(base + i * 4, addr);
+            assert_eq!(10 + i as u32, *v);
+        }
+        assert_eq!(4, r.iter_addr().count());
+    }
+
+    #[test]
+    fn iter() {
+        let mut mem = [1u32, 2, 3, 4];
+        let mut r = Region::<u32>::new(mem.as_mut_ptr() as usize, 4);
+        for v in r.iter_mut().rev().take(2) {
+            *v *= 10;
+        }
+        let mut rev = r.iter().rev();
+        assert_eq!(Some(&40), rev.next());
+        assert_eq!(Some(&30), rev.next());
+        assert_eq!(Some(&2), rev.next());
+        assert_eq!(Some(&1), rev.next());
+        assert_eq!(None, rev.next());
+
+        assert_eq!(Some(&1), r.first());
+        assert_eq!(Some(&40), r.last());
+
+        let empty = Region::<u32>::new(mem.as_mut_ptr() as usize, 0);
+        assert_eq!(None, empty.first());
+        assert_eq!(None, empty.last());
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut mem = [0x11u16, 0x22, 0x33];
+        let r = Region::<u16>::new(mem.as_mut_ptr() as usize, 3);
+        let mut out = [0u16; 3];
+        for (o, v) in out.iter_mut().zip(r) {
+            *o = v;
+        }
+        assert_eq!([0x11, 0x22, 0x33], out);
+        assert_eq!(3, Region::<u16>::new(mem.as_mut_ptr() as usize, 3).into_iter().count());
+    }
+
+    #[test]
+    fn iter_volatile_mut() {
+        let mut mem = [1u32, 2, 3, 4];
+        let mut r = Region::<u32>::new(mem.as_mut_ptr() as usize, 4);
+        for cell in r.iter_volatile_mut() {
+            cell.write(cell.read() + 1);
+        }
+        assert_eq!(4, r.iter_volatile_mut().count());
+        assert_eq!([2, 3, 4, 5], mem);
+    }
+
+    #[test]
+    fn len() {
+        let mut mem = [0u32; 4];
+        assert_eq!(4, Region::<u32>::new(mem.as_mut_ptr() as usize, 4).len());
+        assert_eq!(false, Region::<u32>::new(mem.as_mut_ptr() as usize, 4).is_empty());
+        assert_eq!(true, Region::<u32>::new(mem.as_mut_ptr() as usize, 0).is_empty());
+    }
+
+    #[test]
+    fn clear() {
+        let mut mem = [1u32, 2, 3, 4];
+        let mut r = Region::<u32>::new(mem.as_mut_ptr() as usize, 4);
+        assert_eq!(false, r.is_all(&0));
+        r.clear();
+        assert_eq!(true, r.is_all(&u32::default()));
+        assert_eq!([0; 4], mem);
+    }
+
+    #[test]
+    fn fill_with() {
+        let mut mem = [0u16; 8];
+        let mut r = Region::<u16>::new(mem.as_mut_ptr() as usize, 8);
+        r.fill_with(|i| i as u16);
+        assert_eq!(0, r.get_volatile(0));
+        assert_eq!(5, r.get_volatile(5));
+
+        r.fill_with(|i| 0x5555 ^ i as u16);
+        assert_eq!(0x5552, r.get_volatile(7));
+        assert_eq!(None, r.position(|v| *v & 0xFFF8 != 0x5550));
+    }
+
+    #[test]
+    fn memtest() {
+        let mut mem = [0u16; 8];
+        let mut r = Region::<u16>::new(mem.as_mut_ptr() as usize, 8);
+        assert_eq!(Ok(()), r.memtest(0xA5A5));
+        assert_eq!([0xA5A5; 8], mem);
+        assert_eq!(Ok(()), r.memtest_walking());
+        assert_eq!([0x8000; 8], mem);
+    }
+
+    #[test]
+    fn memtest_stuck() {
+        // Fake memory where index 5 is stuck at zero
+        let mem: [Cell<u16>; 8] = Default::default();
+        let res = memtest_by(8, 0x5555, |i, v| if i != 5 { mem[i].set(v) }, |i| mem[i].get());
+        assert_eq!(Err(5), res);
+    }
+
+    #[test]
+    fn position() {
+        let mut mem = [1u32, 2, 0xAA55, 4, 0xAA55];
+        let r = Region::<u32>::new(mem.as_mut_ptr() as usize, 5);
+        assert_eq!(Some(2), r.position(|v| *v == 0xAA55));
+        assert_eq!(None, r.position(|v| *v == 0x55AA));
+        assert_eq!(true, r.contains(&4));
+        assert_eq!(false, r.contains(&5));
+    }
+
+    #[test]
+    fn eq_slice() {
+        let mut mem = [1u16, 2, 3];
+        let r = Region::<u16>::new(mem.as_mut_ptr() as usize, 3);
+        assert!(r.eq_slice(&[1, 2, 3]));
+        assert!(!r.eq_slice(&[1, 2, 4]));
+        assert!(!r.eq_slice(&[1, 2]));
+        assert!(!r.eq_slice(&[1, 2, 3, 4]));
+
+        assert!(r == [1, 2, 3][..]);
+        assert!(r != [3, 2, 1][..]);
+    }
+
+    #[test]
+    fn clone_to() {
+        let mut src_mem = [1u16, 2, 3, 4];
+        let mut dst_mem = [0u16; 6];
+        let src = Region::<u16>::new(src_mem.as_mut_ptr() as usize, 4);
+        let mut dst = Region::<u16>::new(dst_mem.as_mut_ptr() as usize, 6);
+
+        assert_eq!(4, src.clone_to(&mut dst));
+        assert_eq!([1, 2, 3, 4, 0, 0], dst_mem);
+
+        let mut short_mem = [0u16; 2];
+        let mut short = Region::<u16>::new(short_mem.as_mut_ptr() as usize, 2);
+        assert_eq!(2, src.clone_to(&mut short));
+        assert_eq!([1, 2], short_mem);
+    }
+
+    #[test]
+    fn blit() {
+        let mut src_mem = [1u8, 2, 3, 4];
+        let mut dst_mem = [0u8; 16];
+        let src = Region::<u8>::new(src_mem.as_mut_ptr() as usize, 4);
+        let mut dst = Region::<u8>::new(dst_mem.as_mut_ptr() as usize, 16);
+
+        let mut w = dst.window(5, 11);
+        w.blit(&src, 2, 4, 2);
+        assert_eq!([
+            0, 0, 0, 0,
+            0, 1, 2, 0,
+            0, 3, 4, 0,
+            0, 0, 0, 0,
+        ], dst_mem);
+    }
+
+    #[test]
+    #[should_panic(expected = "blit out of bounds")]
+    fn blit_out_of_bounds() {
+        let mut src_mem = [0u8; 4];
+        let mut dst_mem = [0u8; 16];
+        let src = Region::<u8>::new(src_mem.as_mut_ptr() as usize, 4);
+        let mut dst = Region::<u8>::new(dst_mem.as_mut_ptr() as usize, 16);
+        dst.window(12, 4).blit(&src, 2, 4, 2);
+    }
+
+    #[test]
+    fn copy_within() {
+        let mut mem = [1u8, 1, 2, 2, 3, 3];
+        let mut r = Region::<u8>::new(mem.as_mut_ptr() as usize, 6);
+        r.copy_within(2..6, 0);
+        assert_eq!([2, 2, 3, 3, 3, 3], mem);
+
+        let mut mem = [1u8, 1, 2, 2, 3, 3];
+        let mut r = Region::<u8>::new(mem.as_mut_ptr() as usize, 6);
+        r.copy_within(0..4, 2);
+        assert_eq!([1, 1, 1, 1, 2, 2], mem);
+    }
+
+    #[test]
+    #[should_panic(expected = "copy destination out of bounds")]
+    fn copy_within_out_of_bounds() {
+        let mut mem = [0u8; 6];
+        let mut r = Region::<u8>::new(mem.as_mut_ptr() as usize, 6);
+        r.copy_within(0..4, 3);
+    }
+
+    #[test]
+    fn split_at_mut() {
+        let mut mem = [0u16; 8];
+        let r = Region::<u16>::new(mem.as_mut_ptr() as usize, 8);
+        let (mut front, mut back) = r.split_at_mut(3);
+        assert_eq!(3, front.len());
+        assert_eq!(5, back.len());
+
+        front.write_index(0, 0xAA);
+        back.write_index(0, 0xBB);
+        assert_eq!(0xAA, *front.read_index(0));
+        assert_eq!(0xBB, *back.read_index(0));
+        assert_eq!([0xAA, 0, 0, 0xBB, 0, 0, 0, 0], mem);
+    }
+
+    #[test]
+    #[should_panic(expected = "split index out of bounds")]
+    fn split_at_mut_out_of_bounds() {
+        let mut mem = [0u16; 8];
+        let r = Region::<u16>::new(mem.as_mut_ptr() as usize, 8);
+        r.split_at_mut(9);
+    }
+
+    #[test]
+    fn rows() {
+        let mut mem = [0u8; 12];
+        let mut r = Region::<u8>::new(mem.as_mut_ptr() as usize, 12);
+        for (y, row) in r.rows_mut(4) {
+            row[0] = y as u8;
+        }
+
+        let mut rows = r.rows(4);
+        assert_eq!(Some((0, &[0, 0, 0, 0][..])), rows.next());
+        assert_eq!(Some((1, &[1, 0, 0, 0][..])), rows.next());
+        assert_eq!(Some((2, &[2, 0, 0, 0][..])), rows.next());
+        assert_eq!(None, rows.next());
+
+        assert_eq!(3, r.rows(5).count());
+        assert_eq!(Some((2, &[0, 0][..])), r.rows(5).last());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn get_set_volatile() {
+        let mut mem = [0u32; 4];
+        let p = mem.as_mut_ptr();
+        let mut r = Region::<u32>::new(p as usize, 4);
+
+        unsafe { p.add(2).write_volatile(0xAA) };
+        assert_eq!(0xAA, r.get_volatile(2));
+
+        r.set_volatile(3, 0xBB);
+        assert_eq!(0xBB, unsafe { p.add(3).read_volatile() });
+        assert_eq!(0xBB, r.get_volatile(3));
+    }
+
+    #[test]
+    fn swap_value() {
+        let mut mem = [0u32, 0xAA, 0];
+        let p = mem.as_mut_ptr();
+        let mut r = Region::<u32>::new(p as usize, 3);
+        assert_eq!(0xAA, r.swap_value(1, 0xBB));
+        assert_eq!(0xBB, unsafe { p.add(1).read_volatile() });
+        assert_eq!(0xBB, r.swap_value(1, 0xCC));
+    }
+
+    #[test]
+    #[should_panic]
+    fn swap_value_out_of_bounds() {
+        let mut mem = [0u32; 3];
+        let mut r = Region::<u32>::new(mem.as_mut_ptr() as usize, 3);
+        r.swap_value(3, 0);
+    }
+
+    #[test]
+    fn write_index() {
+        let mut mem = [0u16; 4];
+        let p = mem.as_mut_ptr();
+        let mut r = Region::<u16>::new(p as usize, 4);
+        r.write_index(1, 0x1234);
+        r.write_index(1, 0x5678);
+        assert_eq!(0x5678, unsafe { p.add(1).read_volatile() });
+        assert_eq!(0x5678, r.get_volatile(1));
+    }
+
+    #[repr(C)]
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    struct TestBlock {
+        ctrl: u16,
+        status: u8,
+        flags: u8,
+        data: u32,
+    }
+
+    #[test]
+    fn read_write_struct() {
+        let mut mem = [0u32; 4];
+        let mut r = Region::<u8>::new(mem.as_mut_ptr() as usize, 16);
+        let block = TestBlock{ ctrl: 0x1234, status: 0x56, flags: 0x78, data: 0xAABB_CCDD };
+
+        r.write_struct(8, block);
+        assert_eq!(block, r.read_struct::<TestBlock>(8));
+        assert_eq!(0xAABB_CCDD, r.read_struct::<u32>(12));
+        assert_eq!(0x56, r.get_volatile(10));
+        assert_eq!([0, 0], mem[..2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "struct out of bounds")]
+    fn read_struct_out_of_bounds() {
+        let mut mem = [0u32; 4];
+        let r = Region::<u8>::new(mem.as_mut_ptr() as usize, 16);
+        let _ = r.read_struct::<TestBlock>(12);
+    }
+
+    #[test]
+    #[should_panic(expected = "struct is misaligned")]
+    fn read_struct_misaligned() {
+        let mut mem = [0u32; 4];
+        let r = Region::<u8>::new(mem.as_mut_ptr() as usize, 16);
+        let _ = r.read_struct::<u32>(2);
+    }
+
+    #[test]
+    fn map_in_place() {
+        let mut mem = [1u32, 2, 3, 4];
+        let mut r = Region::<u32>::new(mem.as_mut_ptr() as usize, 4);
+        r.map_in_place(|v| v + 1);
+        assert_eq!(2, *r.read_index(0));
+        assert_eq!(5, *r.read_index(3));
+
+        let mut r = Region::<u32>::new(mem.as_mut_ptr() as usize, 0);
+        r.map_in_place(|v| v + 1);
+    }
+
+    #[test]
+    fn from_array() {
+        static mut BUFF: [u32; 8] = [0, 1, 2, 3, 4, 5, 6, 7];
+        let r = Region::from_array(unsafe { &mut *::core::ptr::addr_of_mut!(BUFF) });
+        assert_eq!(8, r.len());
+        assert_eq!(5, *r.read_index(5));
+
+        assert_eq!(Some(&[0, 1, 2, 3, 4, 5, 6, 7]), r.as_array::<8>());
+        assert_eq!(None, r.as_array::<4>());
+    }
+
+    #[test]
+    fn reinterpret() {
+        let mut mem = [0u32; 2];
+        let mut r = Region::<u8>::new(mem.as_mut_ptr() as usize, 8);
+        r.write_index(0, 0x78);
+        r.write_index(7, 0x12);
+
+        let w = unsafe { r.reinterpret::<u32>() };
+        assert_eq!(2, w.0.len());
+        assert_eq!(u32::from_ne_bytes([0x78, 0, 0, 0]), *w.read_index(0));
+        assert_eq!(u32::from_ne_bytes([0, 0, 0, 0x12]), *w.read_index(1));
+    }
+
+    #[test]
+    fn pixels() {
+        let mut mem = [0u8; 4];
+        let mut r = Region::<u8>::new(mem.as_mut_ptr() as usize, 4);
+        r.set_pixel(10, true);
+        assert_eq!([0, 0b100, 0, 0], mem);
+        assert_eq!(true, r.get_pixel(10));
+        assert_eq!(false, r.get_pixel(9));
+
+        r.set_pixel(31, true);
+        r.set_pixel(10, false);
+        assert_eq!([0, 0, 0, 0x80], mem);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a multiple")]
+    fn reinterpret_uneven() {
+        let mut mem = [0u32; 2];
+        let r = Region::<u8>::new(mem.as_mut_ptr() as usize, 6);
+        let _ = unsafe { r.reinterpret::<u32>() };
+    }
+
+    #[test]
+    #[should_panic(expected = "misaligned")]
+    fn reinterpret_misaligned() {
+        let mut mem = [0u32; 3];
+        let r = Region::<u8>::new(mem.as_mut_ptr() as usize + 1, 8);
+        let _ = unsafe { r.reinterpret::<u32>() };
+    }
+
+    #[test]
+    #[should_panic(expected = "zero-sized")]
+    fn reinterpret_zero_sized() {
+        let mut mem = [0u32; 2];
+        let r = Region::<u8>::new(mem.as_mut_ptr() as usize, 8);
+        let _ = unsafe { r.reinterpret::<()>() };
+    }
+}
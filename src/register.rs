@@ -1,8 +1,17 @@
 // Generic register type for builder style register interaction
 // Copyright 2018 Ryan Kurte
 
-use core::ptr::{read_volatile, write_volatile};
-use core::ops::{Add, Sub, Not, BitAnd, BitOr, Shl, Shr, BitAndAssign, BitOrAssign};
+use core::cmp::Ordering;
+use core::fmt;
+#[cfg(feature = "atomics")]
+use core::sync::atomic::compiler_fence;
+#[cfg(any(feature = "atomics", feature = "trace"))]
+use core::sync::atomic::Ordering as AtomicOrdering;
+#[cfg(feature = "trace")]
+use core::sync::atomic::AtomicPtr;
+use core::ops::{Add, Sub, Mul, Div, Not, BitAnd, BitOr, Shl, Shr, BitAndAssign, BitOrAssign};
+
+use ::cell::VolatileCell;
 
 // Zero trait for RegisterType implementations
 #[doc = "Zero trait allows types to be created with a value of zero"]
@@ -16,12 +25,56 @@ pub trait One {
     fn one() -> Self;
 }
 
+// BitWidth trait for RegisterType implementations
+#[doc = "BitWidth trait exposes the number of bits in a type and a mask covering all of them"]
+pub trait BitWidth {
+    fn bit_width() -> u32;
+    fn all_ones() -> Self;
+}
+
+// Signed trait for RegisterType implementations
+#[doc = "Signed trait allows types to be reinterpreted as two's complement signed values of the same width"]
+pub trait Signed {
+    fn to_signed(self) -> i64;
+    fn from_signed(v: i64) -> Self;
+}
+
+// Truncate trait for RegisterType implementations
+#[doc = "Truncate trait allows types to be created from a u64, discarding any bits above the type width"]
+pub trait Truncate {
+    fn truncate(v: u64) -> Self;
+}
+
+// Rotate trait for RegisterType implementations
+#[doc = "Rotate trait allows types to be rotated, wrapping bits shifted out back in at the other end"]
+pub trait Rotate {
+    fn rotate_left(self, n: u32) -> Self;
+    fn rotate_right(self, n: u32) -> Self;
+}
+
+// WrappingOps trait for RegisterType implementations
+#[doc = "WrappingOps trait allows types to be added and subtracted, wrapping around at the type width"]
+pub trait WrappingOps {
+    fn wrapping_add(self, v: Self) -> Self;
+    fn wrapping_sub(self, v: Self) -> Self;
+}
+
+// ToBytes trait for RegisterType implementations
+#[doc = "ToBytes trait allows types to be converted to and from byte arrays of a defined endianness"]
+pub trait ToBytes: Sized {
+    type Bytes;
+    fn to_le_bytes(self) -> Self::Bytes;
+    fn to_be_bytes(self) -> Self::Bytes;
+    fn from_le_bytes(b: Self::Bytes) -> Self;
+    fn from_be_bytes(b: Self::Bytes) -> Self;
+}
+
 #[doc = "RegisterType trait allows register implementations to be generic over unsigned integer types"]
-pub trait RegisterType<T>: Zero + One
+pub trait RegisterType<T>: Zero + One + BitWidth + Truncate + Rotate + WrappingOps + ToBytes
                     + Not<Output=T> + Add<T, Output=T> + Sub<T, Output=T>
                     + BitAnd<T, Output=T> + BitOr<T, Output=T> + BitAndAssign<T> + BitOrAssign<T> 
                     + Shl<T, Output=T> + Shr<T, Output=T>
-                    + Clone + Copy + Default + PartialEq {}
+                    + Clone + Copy + Default + PartialEq + Into<u64> {}
 
 #[doc = "Helper macro to generate RegisterType implementations for a given type"]
 #[macro_export]
@@ -34,6 +87,38 @@ macro_rules! register_impl {
         impl Zero for $t {
             fn zero() -> $t { 0 }
         }
+        impl BitWidth for $t {
+            fn bit_width() -> u32 { (::core::mem::size_of::<$t>() * 8) as u32 }
+            fn all_ones() -> $t { !0 }
+        }
+        impl Signed for $t {
+            fn to_signed(self) -> i64 {
+                let shift = 64 - <$t as BitWidth>::bit_width();
+                ((self as u64) << shift) as i64 >> shift
+            }
+            fn from_signed(v: i64) -> $t { v as $t }
+        }
+        impl Truncate for $t {
+            fn truncate(v: u64) -> $t { v as $t }
+        }
+        impl Rotate for $t {
+            fn rotate_left(self, n: u32) -> $t { <$t>::rotate_left(self, n) }
+            fn rotate_right(self, n: u32) -> $t { <$t>::rotate_right(self, n) }
+        }
+        impl From<$crate::register::Register<$t>> for $t {
+            fn from(r: $crate::register::Register<$t>) -> $t { r.1 }
+        }
+        impl WrappingOps for $t {
+            fn wrapping_add(self, v: $t) -> $t { <$t>::wrapping_add(self, v) }
+            fn wrapping_sub(self, v: $t) -> $t { <$t>::wrapping_sub(self, v) }
+        }
+        impl ToBytes for $t {
+            type Bytes = [u8; ::core::mem::size_of::<$t>()];
+            fn to_le_bytes(self) -> Self::Bytes { <$t>::to_le_bytes(self) }
+            fn to_be_bytes(self) -> Self::Bytes { <$t>::to_be_bytes(self) }
+            fn from_le_bytes(b: Self::Bytes) -> $t { <$t>::from_le_bytes(b) }
+            fn from_be_bytes(b: Self::Bytes) -> $t { <$t>::from_be_bytes(b) }
+        }
     }
 }
 
@@ -43,8 +128,93 @@ register_impl!(u16);
 register_impl!(u32);
 register_impl!(u64);
 
+#[doc = "FieldSpec describes the shift and mask of a field within a register"]
+#[doc = "Note that the mask is applied prior to shifting, so should always start at 0b1"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FieldSpec<T> {
+    pub shift: T,
+    pub mask: T,
+}
+
+#[doc = "FieldDescriptor trait describes a field with a compile-time shift and width"]
+pub trait FieldDescriptor {
+    const SHIFT: u32;
+    const WIDTH: u32;
+    const MASK: u64 = if Self::WIDTH >= 64 { !0 } else { (1 << Self::WIDTH) - 1 };
+}
+
+#[doc = "Field type describes a field by shift and width for type-checked access via `read_field` and `write_field`"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Field<const SHIFT: u32, const WIDTH: u32>;
+
+impl <const SHIFT: u32, const WIDTH: u32>FieldDescriptor for Field<SHIFT, WIDTH> {
+    const SHIFT: u32 = SHIFT;
+    const WIDTH: u32 = WIDTH;
+}
+
+#[doc = "RegisterFmt formats a register using the provided field formatting function, see `register!`"]
+pub struct RegisterFmt<'a, T: RegisterType<T>> (pub &'a Register<T>, pub fn(&Register<T>, &mut fmt::Formatter) -> fmt::Result);
+
+impl <'a, T: RegisterType<T>>fmt::Debug for RegisterFmt<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.1)(self.0, f)
+    }
+}
+
+#[doc = "Flags trait maps named flags to register bit indices, see the `flags!` macro"]
+pub trait Flags<T: 'static>: Copy + 'static {
+    const FLAGS: &'static [(Self, T)];
+}
+
+#[doc = "Scaled wraps a field value converted to engineering units, ie. a frequency, to keep it distinct from raw field values"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Scaled<T>(pub T);
+
+#[doc = "Shift wraps a field shift for use with `set_masked_typed`"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Shift<T>(pub T);
+
+#[doc = "Mask wraps a field mask for use with `set_masked_typed`"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Mask<T>(pub T);
+
+#[doc = "FieldVal wraps a field value for use with `set_masked_typed`"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FieldVal<T>(pub T);
+
+// Trace hook storage, null when no hook is installed
+#[cfg(feature = "trace")]
+static TRACE_HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+#[doc = "Installs a hook called with the address and value of every `Register::write` or `Mmio` write, or removes it if `None`"]
+#[doc = "This allows test harnesses to record the write sequence of a driver"]
+#[cfg(feature = "trace")]
+pub fn set_trace_hook(hook: Option<fn(addr: usize, value: u64)>) {
+    let p = match hook {
+        Some(f) => f as *mut (),
+        None => core::ptr::null_mut(),
+    };
+    TRACE_HOOK.store(p, AtomicOrdering::SeqCst);
+}
+
+// Serialises tests that install the global trace hook
+#[cfg(all(test, feature = "trace"))]
+pub(crate) static TRACE_TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+// Calls the installed trace hook, if any
+#[cfg(feature = "trace")]
+fn trace(addr: usize, value: u64) {
+    let p = TRACE_HOOK.load(AtomicOrdering::SeqCst);
+    if !p.is_null() {
+        let hook: fn(usize, u64) = unsafe { core::mem::transmute(p) };
+        hook(addr, value)
+    }
+}
+
 // Register helper structure
 // This uses an internal value and builder approach to simplify interacting with registers.
+#[doc = "Register type wraps a register address and internal value for builder style interaction"]
+#[doc = "Note that `PartialEq` compares both the address and the value, use `value_eq` to compare values only"]
 #[derive(Debug, PartialEq, Clone)]
 pub struct Register<T: RegisterType<T>> (pub usize, pub T);
 
@@ -55,6 +225,26 @@ impl <T: RegisterType<T>>Register<T> {
         Register(addr, T::default())
     }
 
+    #[doc = "Creates a new register of the provided type with the specified address and internal value"]
+    pub fn with(addr: usize, val: T) -> Register<T> {
+        Register(addr, val)
+    }
+
+    #[doc = "Creates a new register at the specified address with the value decoded from little-endian bytes"]
+    pub fn from_le_bytes(addr: usize, bytes: T::Bytes) -> Register<T> {
+        Register(addr, T::from_le_bytes(bytes))
+    }
+
+    #[doc = "Creates a new register at the specified address with the value decoded from big-endian bytes"]
+    pub fn from_be_bytes(addr: usize, bytes: T::Bytes) -> Register<T> {
+        Register(addr, T::from_be_bytes(bytes))
+    }
+
+    #[doc = "Creates a new 8-bit register"]
+    pub fn u8(addr: usize) -> Register<u8> {
+        Register::<u8>::new(addr)
+    }
+
     #[doc = "Creates a new 16-bit ride register"]
     pub fn u16(addr: usize) -> Register<u16> {
         Register::<u16>::new(addr)
@@ -65,15 +255,57 @@ impl <T: RegisterType<T>>Register<T> {
         Register::<u32>::new(addr)
     }
 
+    #[doc = "Creates a new 64-bit register"]
+    pub fn u64(addr: usize) -> Register<u64> {
+        Register::<u64>::new(addr)
+    }
+
+    #[doc = "Returns the number of bits in the register type"]
+    pub fn bit_width() -> u32 {
+        T::bit_width()
+    }
+
+    #[doc = "Returns a mask covering the full width of the register type"]
+    pub fn all_ones() -> T {
+        T::all_ones()
+    }
+
+    #[doc = "Returns the register address"]
+    pub fn addr(&self) -> usize {
+        self.0
+    }
+
+    #[doc = "Creates a new register of the same type at the provided byte offset from this register"]
+    #[doc = "Panics if the resulting address overflows"]
+    pub fn offset(&self, bytes: isize) -> Register<T> {
+        Register::new(self.0.checked_add_signed(bytes).expect("register offset overflow"))
+    }
+
     #[doc = "Reads the register value and returns a new instance with internal value set."]
     pub fn read(&mut self) -> Register<T> {
         let mut reg = self.clone();
-        unsafe {
-            reg.1 = read_volatile(self.0 as *const T)
-        }
+        reg.1 = VolatileCell::new(self.0).get();
         reg
     }
 
+    #[doc = "Returns a zeroed register builder at the same address, without reading the register"]
+    #[doc = "This should be used to configure write-only registers, ie. `reg.fresh().set_bit(0, true).write_no_verify()`"]
+    pub fn fresh(&self) -> Register<T> {
+        Register::new(self.0)
+    }
+
+    #[doc = "Reads the register, applies the provided function to the value, and writes the result"]
+    pub fn modify<F: FnOnce(Register<T>) -> Register<T>>(&mut self, f: F) {
+        f(self.read()).write()
+    }
+
+    #[doc = "Read-modify-write as with `modify`, guarded by a critical section"]
+    #[doc = "This prevents interrupt handlers from modifying the register mid-sequence"]
+    #[cfg(feature = "critical-section")]
+    pub fn modify_cs<F: FnOnce(Register<T>) -> Register<T>>(&mut self, f: F) {
+        ::critical_section::with(|_| self.modify(f))
+    }
+
     #[doc = "clears the internal register value"]
     pub fn zero(&mut self) -> Register<T>  {
         let mut reg = self.clone();
@@ -83,7 +315,41 @@ impl <T: RegisterType<T>>Register<T> {
 
     #[doc = "returns the register value"]
     pub fn value(&self) -> T {
-        self.1.clone()
+        self.1
+    }
+
+    #[doc = "compares the internal values of two registers, ignoring their addresses"]
+    pub fn value_eq(&self, other: &Register<T>) -> bool {
+        self.1 == other.1
+    }
+
+    #[doc = "orders the internal values of two registers, ignoring their addresses"]
+    pub fn value_cmp(&self, other: &Register<T>) -> Option<Ordering> where T: PartialOrd {
+        self.1.partial_cmp(&other.1)
+    }
+
+    #[doc = "returns the register value reinterpreted as a two's complement signed value"]
+    #[doc = "Note that the backing type remains unsigned, so masked accessors continue to use logical shifts"]
+    pub fn read_signed(&self) -> i64 where T: Signed {
+        self.1.to_signed()
+    }
+
+    #[doc = "sets the internal value of the register from a signed value as two's complement"]
+    #[doc = "Values outside the signed range of the register width are truncated to the low bits without error,"]
+    #[doc = "ie. `set_signed(-300)` on an 8-bit register stores `0xD4` (-44)"]
+    pub fn set_signed(mut self, val: i64) -> Register<T> where T: Signed {
+        self.1 = T::from_signed(val);
+        self
+    }
+
+    #[doc = "returns the register value as little-endian bytes"]
+    pub fn to_le_bytes(&self) -> T::Bytes {
+        ToBytes::to_le_bytes(self.1)
+    }
+
+    #[doc = "returns the register value as big-endian bytes"]
+    pub fn to_be_bytes(&self) -> T::Bytes {
+        ToBytes::to_be_bytes(self.1)
     }
 
     #[doc = "sets the internal value of the register"]
@@ -94,7 +360,7 @@ impl <T: RegisterType<T>>Register<T> {
 
     #[doc = "boolean and the provided and current values"]
     pub fn and(mut self, val: T) -> Register<T> {
-        self.1 = self.1 & val;
+        self.1 &= val;
         self
     }
 
@@ -104,19 +370,68 @@ impl <T: RegisterType<T>>Register<T> {
         self
     }
 
+    #[doc = "takes the bits under the mask from the other register and the remaining bits from this register"]
+    pub fn merge(mut self, other: &Register<T>, mask: T) -> Register<T> {
+        self.1 = (self.1 & !mask) | (other.1 & mask);
+        self
+    }
+
+    #[doc = "clears the bits under the mask and sets them from the provided value, without shifting"]
+    #[doc = "Unlike `set_masked` the value must already be positioned within the register"]
+    pub fn insert(mut self, mask: T, val: T) -> Register<T> {
+        self.1 = (self.1 & !mask) | (val & mask);
+        self
+    }
+
     #[doc = "clears the masked area of the provided value"]
     pub fn clear(mut self, mask: T) -> Register<T> {
         self.1 &= !mask;
         self
     }
 
+    #[doc = "rotates the current value left by the provided number of bits"]
+    pub fn rotate_left(mut self, n: T) -> Register<T> {
+        self.1 = Rotate::rotate_left(self.1, Into::<u64>::into(n) as u32);
+        self
+    }
+
+    #[doc = "rotates the current value right by the provided number of bits"]
+    pub fn rotate_right(mut self, n: T) -> Register<T> {
+        self.1 = Rotate::rotate_right(self.1, Into::<u64>::into(n) as u32);
+        self
+    }
+
+    #[doc = "increments the current value by one, wrapping to zero on overflow"]
+    pub fn increment(mut self) -> Register<T> {
+        self.1 = WrappingOps::wrapping_add(self.1, T::one());
+        self
+    }
+
+    #[doc = "decrements the current value by one, wrapping to all ones on underflow"]
+    pub fn decrement(mut self) -> Register<T> {
+        self.1 = WrappingOps::wrapping_sub(self.1, T::one());
+        self
+    }
+
+    #[doc = "returns true if any of the bits in the provided mask are set"]
+    pub fn any(&self, mask: T) -> bool {
+        self.1 & mask != T::zero()
+    }
+
+    #[doc = "returns true if all of the bits in the provided mask are set"]
+    pub fn all(&self, mask: T) -> bool {
+        self.1 & mask == mask
+    }
+
     #[doc = "returns a boolean consisting to the indexed bit"]
     pub fn get_bit(&self, i: T) -> bool {
-        self.1.clone() & (T::one() << i) != T::zero()
+        debug_assert!(Self::in_range(i), "bit index out of range");
+        self.1 & (T::one() << i) != T::zero()
     }
 
     #[doc = "Sets a bit in the current value"]
     pub fn set_bit(mut self, i: T, v: bool) -> Register<T> {
+        debug_assert!(Self::in_range(i), "bit index out of range");
         self.1 = match v {
             true => self.1 | (T::one() << i),
             false => self.1 & !(T::one() << i),
@@ -124,31 +439,292 @@ impl <T: RegisterType<T>>Register<T> {
         self
     }
 
+    #[doc = "Sets an active-low bit in the current value, clearing the bit when enabled and setting it when disabled"]
+    pub fn set_bit_active_low(self, i: T, enabled: bool) -> Register<T> {
+        self.set_bit(i, !enabled)
+    }
+
+    #[doc = "Sets a bit in the current value only if the provided condition is true"]
+    pub fn set_bit_if(self, cond: bool, i: T, v: bool) -> Register<T> {
+        self.when(cond, |r| r.set_bit(i, v))
+    }
+
+    #[doc = "Applies the provided function only if the provided condition is true"]
+    pub fn when<F: FnOnce(Register<T>) -> Register<T>>(self, cond: bool, f: F) -> Register<T> {
+        match cond {
+            true => f(self),
+            false => self,
+        }
+    }
+
+    #[doc = "Returns an iterator over the indices of set bits, from LSB to MSB"]
+    pub fn set_bits_iter(&self) -> impl Iterator<Item = usize> {
+        let value = self.1;
+        let mut bit = T::one();
+        (0..T::bit_width() as usize).filter(move |_| {
+            let set = value & bit != T::zero();
+            bit = bit << T::one();
+            set
+        })
+    }
+
+    #[doc = "Returns an iterator over the named flags whose bits are set, in declaration order"]
+    pub fn flags<F: Flags<T>>(&self) -> impl Iterator<Item = F> where T: 'static {
+        let reg = self.clone();
+        F::FLAGS.iter().filter(move |f| reg.get_bit(f.1)).map(|f| f.0)
+    }
+
+    #[doc = "Returns the index of the lowest set bit, or `None` if no bits are set"]
+    pub fn lowest_set_bit(&self) -> Option<usize> {
+        self.set_bits_iter().next()
+    }
+
+    #[doc = "Returns the index of the highest set bit, or `None` if no bits are set"]
+    pub fn highest_set_bit(&self) -> Option<usize> {
+        self.set_bits_iter().last()
+    }
+
     #[doc = "Fetches a value with the provided mask and shift"]
     #[doc = "Note that shift is applied prior to masking, so mask should always start at 0b1"]
     pub fn get_masked(&self, shift: T, mask: T) -> T  {
+        debug_assert!(Self::in_range(shift), "shift out of range");
         read_masked!(self.1, shift, mask)
     }
 
+    #[doc = "Fetches a value with the provided mask and shift, sign-extended from the top bit of the mask"]
+    #[doc = "This decodes two's complement fields, ie. a 4-bit field of `0b1111` returns `-1`."]
+    #[doc = "Fields wider than 32 bits are truncated"]
+    pub fn get_masked_signed(&self, shift: T, mask: T) -> i32 {
+        let width = 64 - Into::<u64>::into(mask).leading_zeros();
+        if width == 0 {
+            return 0;
+        }
+        let v: u64 = self.get_masked(shift, mask).into();
+        ((v << (64 - width)) as i64 >> (64 - width)) as i32
+    }
+
+    #[doc = "Fetches a value with the provided mask and shift, multiplied by the provided factor"]
+    #[doc = "The multiplication uses the register type, so panics on overflow in debug builds and wraps in release builds"]
+    pub fn get_scaled(&self, shift: T, mask: T, factor: T) -> Scaled<T> where T: Mul<T, Output=T> {
+        Scaled(self.get_masked(shift, mask) * factor)
+    }
+
+    #[doc = "Sets a value with a provided mask and shift, divided by the provided factor"]
+    #[doc = "The division truncates, so values should be multiples of the factor, and panics if the factor is zero"]
+    pub fn set_scaled(self, shift: T, mask: T, val: Scaled<T>, factor: T) -> Register<T> where T: Div<T, Output=T> {
+        self.set_masked(shift, mask, val.0 / factor)
+    }
+
     #[doc = "Sets a value with a provided mask and shift"]
     #[doc = "Note that mask is applied before shifting, so mask should always start at 0b1"]
     pub fn set_masked(mut self, shift: T, mask: T, val: T) -> Register<T>  {
         //self.clear(mask.clone()).or((val & mask) << shift);
+        debug_assert!(Self::in_range(shift), "shift out of range");
         write_masked!(self.1, shift, mask, val);
         self
     }
 
+    #[doc = "Sets each `(shift, mask, value)` field in the provided table in order, as with `set_masked`"]
+    #[doc = "This allows register programs to be stored as const arrays"]
+    pub fn set_fields(mut self, writes: &[(T, T, T)]) -> Register<T> {
+        for &(shift, mask, val) in writes {
+            debug_assert!(Self::in_range(shift), "shift out of range");
+            write_masked!(self.1, shift, mask, val);
+        }
+        self
+    }
+
+    #[doc = "Fetches the value of the field described by the provided field type"]
+    pub fn read_field<F: FieldDescriptor>(&self) -> T {
+        debug_assert!(F::SHIFT + F::WIDTH <= T::bit_width(), "field out of range");
+        T::truncate((Into::<u64>::into(self.1) >> F::SHIFT) & F::MASK)
+    }
+
+    #[doc = "Sets the value of the field described by the provided field type"]
+    #[doc = "Bits of the value outside the field width are discarded"]
+    pub fn write_field<F: FieldDescriptor>(mut self, val: T) -> Register<T> {
+        debug_assert!(F::SHIFT + F::WIDTH <= T::bit_width(), "field out of range");
+        let mask = F::MASK << F::SHIFT;
+        let cur: u64 = self.1.into();
+        let val: u64 = val.into();
+        self.1 = T::truncate((cur & !mask) | ((val << F::SHIFT) & mask));
+        self
+    }
+
+    #[doc = "Sets a value with a provided mask and shift using typed arguments, so transposed arguments fail to compile"]
+    #[doc = "```"]
+    #[doc = "# use embedded_builder::register::{Register, Shift, Mask, FieldVal};"]
+    #[doc = "let r = Register::<u16>(0, 0).set_masked_typed(Shift(4), Mask(0xF), FieldVal(0xA));"]
+    #[doc = "assert_eq!(0x00A0, r.value());"]
+    #[doc = "```"]
+    #[doc = "```compile_fail"]
+    #[doc = "# use embedded_builder::register::{Register, Shift, Mask, FieldVal};"]
+    #[doc = "let r = Register::<u16>(0, 0).set_masked_typed(FieldVal(0xA), Mask(0xF), Shift(4));"]
+    #[doc = "```"]
+    pub fn set_masked_typed(self, shift: Shift<T>, mask: Mask<T>, val: FieldVal<T>) -> Register<T> {
+        self.set_masked(shift.0, mask.0, val.0)
+    }
+
+    #[doc = "Sets a value with a provided mask and shift, returning `Err(val)` if the value does not fit the mask"]
+    #[doc = "Unlike `set_masked`, oversized values are rejected rather than truncated"]
+    pub fn set_masked_checked(self, shift: T, mask: T, val: T) -> Result<Register<T>, T> {
+        match val & !mask == T::zero() {
+            true => Ok(self.set_masked(shift, mask, val)),
+            false => Err(val),
+        }
+    }
+
+    #[doc = "Writes the internal value to the register, preserving the bits under the reserved mask"]
+    #[doc = "The current register value is read so reserved bits are written back unchanged,"]
+    #[doc = "and the merged value is written with `write` so it is traced and verified as usual"]
+    pub fn write_preserving(&mut self, reserved_mask: T) {
+        let current = self.read().value();
+        let val = (current & reserved_mask) | (self.1 & !reserved_mask);
+        Register(self.0, val).write()
+    }
+
+    #[doc = "Writes `new` to the register if its current value equals `current`"]
+    #[doc = "Returns `Ok` with the previous value on success, or `Err` with the actual value on mismatch."]
+    #[doc = "Note this is a fenced volatile read-compare-write and is not atomic on MMIO unless the"]
+    #[doc = "peripheral itself provides exclusive access (ie. LDREX/STREX support)."]
+    #[doc = "A successful store is passed to the trace hook but not read back, as with `write_no_verify`"]
+    #[cfg(feature = "atomics")]
+    pub fn compare_exchange(&self, current: T, new: T) -> Result<T, T> {
+        compiler_fence(AtomicOrdering::SeqCst);
+        let actual = VolatileCell::new(self.0).get();
+        if actual != current {
+            return Err(actual);
+        }
+        Register(self.0, new).write_no_verify();
+        compiler_fence(AtomicOrdering::SeqCst);
+        Ok(actual)
+    }
+
+    #[doc = "Polls the register until the field with the provided mask and shift matches the target"]
+    #[doc = "Returns `Err(())` if the field does not match within `max_iters` reads"]
+    #[allow(clippy::result_unit_err)]
+    pub fn wait_for_masked(&mut self, shift: T, mask: T, target: T, max_iters: u32) -> Result<(), ()> {
+        for _ in 0..max_iters {
+            if self.read().get_masked(shift, mask) == target {
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+
     #[doc = "Writes the internal value to the register"]
+    #[doc = "With the `trace` feature enabled the write is first passed to the hook set by `set_trace_hook`."]
+    #[doc = "With the `verify-writes` feature enabled in builds with `debug_assertions`, the register is read back"]
+    #[doc = "and asserted to hold the written value, so write-only, self-clearing, and write-one-to-clear registers"]
+    #[doc = "must use `write_no_verify` instead. Release builds do not read back the register"]
     pub fn write(self) {
-        unsafe {
-            write_volatile(self.0 as *mut T, self.1)
+        #[cfg(all(feature = "verify-writes", debug_assertions))]
+        let (addr, val) = (self.0, self.1);
+        self.write_no_verify();
+        #[cfg(all(feature = "verify-writes", debug_assertions))]
+        {
+            let read = VolatileCell::<T>::new(addr).get();
+            assert!(read == val, "register write verify failed at 0x{:x}, wrote 0x{:x} read 0x{:x}",
+                addr, Into::<u64>::into(val), Into::<u64>::into(read));
+        }
+    }
+
+    #[doc = "Writes the internal value to the register only if it differs from the current register value"]
+    #[doc = "Returns true if the write was performed, reducing redundant writes on slow buses"]
+    pub fn write_if_changed(self) -> bool {
+        if VolatileCell::<T>::new(self.0).get() == self.1 {
+            return false;
+        }
+        self.write();
+        true
+    }
+
+    #[doc = "Writes the internal value to the register without read-back verification"]
+    #[doc = "This is equivalent to `write` unless the `verify-writes` feature is enabled"]
+    pub fn write_no_verify(self) {
+        #[cfg(feature = "trace")]
+        trace(self.0, self.1.into());
+        VolatileCell::new(self.0).set(self.1)
+    }
+
+    // Checks a shift or bit index is below the bit width of the register type
+    fn in_range(shift: T) -> bool {
+        Into::<u64>::into(shift) < T::bit_width() as u64
+    }
+}
+
+// Serde implementations for host-side tooling
+// Registers are serialized as `{ "addr": ..., "value": ... }`
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+    use super::{Register, RegisterType};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename = "Register")]
+    struct RegisterRepr<T> {
+        addr: usize,
+        value: T,
+    }
+
+    impl <T: RegisterType<T> + Serialize>Serialize for Register<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            RegisterRepr{ addr: self.0, value: self.1 }.serialize(serializer)
+        }
+    }
+
+    impl <'de, T: RegisterType<T> + Deserialize<'de>>Deserialize<'de> for Register<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Register<T>, D::Error> {
+            let r = RegisterRepr::<T>::deserialize(deserializer)?;
+            Ok(Register(r.addr, r.value))
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use ::register::Register;
+    use core::cmp::Ordering;
+    use ::register::{Register, Scaled, Field, FieldDescriptor, Shift, Mask, FieldVal};
+
+    #[test]
+    fn bit_width() {
+        assert_eq!(8, Register::<u8>::bit_width());
+        assert_eq!(16, Register::<u16>::bit_width());
+        assert_eq!(64, Register::<u64>::bit_width());
+        assert_eq!(0xFFFF, Register::<u16>::all_ones());
+        assert_eq!(0xFFFF_FFFF, Register::<u32>::all_ones());
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(Register::<u8>(0x10, 0), Register::<u8>::u8(0x10));
+        assert_eq!(Register::<u64>(0x20, 0), Register::<u64>::u64(0x20));
+        assert_eq!(0, Register::<u8>::u8(0x10).value());
+        assert_eq!(0, Register::<u64>::u64(0x20).value());
+    }
+
+    #[test]
+    fn offset() {
+        let r = Register::<u32>(0x1000, 0xFF);
+        assert_eq!(0x1000, r.addr());
+        assert_eq!(Register::<u32>(0x1004, 0), r.offset(4));
+        assert_eq!(0x0FFC, r.offset(-4).addr());
+    }
+
+    #[test]
+    #[should_panic(expected = "register offset overflow")]
+    fn offset_overflow() {
+        Register::<u32>(0x10, 0).offset(-0x20);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let r = Register::<u16>(0x4000_1000, 0xABCD);
+        let s = ::serde_json::to_string(&r).unwrap();
+        assert_eq!(r#"{"addr":1073745920,"value":43981}"#, s);
+        assert_eq!(r, ::serde_json::from_str::<Register<u16>>(&s).unwrap());
+    }
 
     #[test]
     fn set() {
@@ -158,6 +734,64 @@ mod tests {
         assert_eq!(100, r.value());
     }
 
+    #[test]
+    fn fresh() {
+        let mut mem: u16 = 0xDEAD;
+        let r = Register::<u16>(&mut mem as *mut u16 as usize, 0x1234);
+        let f = r.fresh();
+        assert_eq!(r.addr(), f.addr());
+        assert_eq!(0, f.value());
+
+        f.set_bit(0, true).write();
+        assert_eq!(0x0001, mem);
+    }
+
+    #[test]
+    fn modify() {
+        let mut mem: u16 = 0x00F0;
+        let mut r = Register::<u16>::new(&mut mem as *mut u16 as usize);
+        r.modify(|r| r.set_bit(0, true));
+        assert_eq!(0x00F1, r.read().value());
+    }
+
+    #[cfg(feature = "critical-section")]
+    #[test]
+    fn modify_cs() {
+        let mut mem: u16 = 0x00F0;
+        let mut r = Register::<u16>::new(&mut mem as *mut u16 as usize);
+        r.modify_cs(|r| r.clear(0x00F0).set_bit(1, true));
+        assert_eq!(0x0002, r.read().value());
+    }
+
+    #[test]
+    fn signed() {
+        assert_eq!(-1, Register::<u16>(0, 0xFFFF).read_signed());
+        assert_eq!(0x7FFF, Register::<u16>(0, 0x7FFF).read_signed());
+        assert_eq!(-128, Register::<u8>(0, 0x80).read_signed());
+        assert_eq!(-2, Register::<u64>(0, 0xFFFF_FFFF_FFFF_FFFE).read_signed());
+
+        let r = Register::<u16>(0, 0).set_signed(-1);
+        assert_eq!(0xFFFF, r.value());
+        let r = r.set_signed(-300);
+        assert_eq!(-300, r.read_signed());
+        assert_eq!(0xD4, Register::<u8>(0, 0).set_signed(-300).value());
+    }
+
+    #[test]
+    fn bytes() {
+        let r = Register::<u16>(0x10, 0x1234);
+        assert_eq!([0x34, 0x12], r.to_le_bytes());
+        assert_eq!([0x12, 0x34], r.to_be_bytes());
+        assert_eq!(r, Register::<u16>::from_le_bytes(0x10, r.to_le_bytes()));
+        assert_eq!(r, Register::<u16>::from_be_bytes(0x10, r.to_be_bytes()));
+
+        let r = Register::<u32>(0x20, 0x1234_5678);
+        assert_eq!([0x78, 0x56, 0x34, 0x12], r.to_le_bytes());
+        assert_eq!([0x12, 0x34, 0x56, 0x78], r.to_be_bytes());
+        assert_eq!(r, Register::<u32>::from_le_bytes(0x20, r.to_le_bytes()));
+        assert_eq!(r, Register::<u32>::from_be_bytes(0x20, r.to_be_bytes()));
+    }
+
     #[test]
     fn zero() {
         let mut r = Register::<u16>(0, 100);
@@ -166,6 +800,23 @@ mod tests {
         assert_eq!(0, r.value());
     }
 
+    #[test]
+    fn value_eq() {
+        let a = Register::<u16>(0x1000, 100);
+        let b = Register::<u16>(0x2000, 100);
+        assert_eq!(true, a.value_eq(&b));
+        assert_ne!(a, b);
+        assert_eq!(false, a.value_eq(&Register::<u16>(0x1000, 101)));
+    }
+
+    #[test]
+    fn value_cmp() {
+        let a = Register::<u16>(0x1000, 100);
+        assert_eq!(Some(Ordering::Equal), a.value_cmp(&Register::<u16>(0x2000, 100)));
+        assert_eq!(Some(Ordering::Less), a.value_cmp(&Register::<u16>(0x0000, 200)));
+        assert_eq!(Some(Ordering::Greater), a.value_cmp(&Register::<u16>(0x2000, 50)));
+    }
+
     #[test]
     fn and() {
         let mut r = Register::<u16>(0, 0xFFFF);
@@ -180,6 +831,15 @@ mod tests {
         assert_eq!(0xFFF0, r.value());
     }
 
+    #[test]
+    fn merge() {
+        let r = Register::<u16>(0, 0x1234);
+        let preset = Register::<u16>(4, 0xABCD);
+        let r = r.merge(&preset, 0x0FF0);
+        assert_eq!(0x1BC4, r.value());
+        assert_eq!(0, r.addr());
+    }
+
     #[test]
     fn clear() {
         let mut r = Register::<u16>(0, 0xF0F0);
@@ -187,6 +847,26 @@ mod tests {
         assert_eq!(0x00F0, r.value());
     }
 
+    #[test]
+    fn rotate() {
+        let r = Register::<u16>(0, 0x8001);
+        assert_eq!(0x0003, r.clone().rotate_left(1).value());
+        assert_eq!(0xC000, r.clone().rotate_right(1).value());
+        assert_eq!(0x8001, r.rotate_left(4).rotate_right(4).value());
+        assert_eq!(0x0180, Register::<u16>(0, 0x8001).rotate_left(8).value());
+    }
+
+    #[test]
+    fn any_all() {
+        let r = Register::<u16>(0, 0b0110);
+        assert_eq!(true,  r.any(0b0011));
+        assert_eq!(false, r.all(0b0011));
+        assert_eq!(true,  r.any(0b0110));
+        assert_eq!(true,  r.all(0b0110));
+        assert_eq!(false, r.any(0b1001));
+        assert_eq!(false, r.all(0b1001));
+    }
+
     #[test]
     fn get_bit() {
         let r = Register::<u16>(0, 0b0101);
@@ -205,14 +885,284 @@ mod tests {
         assert_eq!(0b0001, r.value());
     }
 
+    #[test]
+    fn set_bit_if() {
+        let mut r = Register::<u16>(0, 0b0001);
+        r = r.set_bit_if(true, 2, true);
+        assert_eq!(0b0101, r.value());
+        r = r.set_bit_if(false, 0, false);
+        assert_eq!(0b0101, r.value());
+    }
+
+    #[test]
+    fn when() {
+        let mut r = Register::<u16>(0, 0x00F0);
+        r = r.when(true, |r| r.or(0x0F00));
+        assert_eq!(0x0FF0, r.value());
+        r = r.when(false, |r| r.clear(0xFFFF));
+        assert_eq!(0x0FF0, r.value());
+    }
+
+    #[test]
+    fn set_bits_iter() {
+        let r = Register::<u16>(0, 0b1010);
+        let mut i = r.set_bits_iter();
+        assert_eq!(Some(1), i.next());
+        assert_eq!(Some(3), i.next());
+        assert_eq!(None, i.next());
+
+        let r = Register::<u16>(0, 0x8000);
+        assert_eq!(Some(15), r.set_bits_iter().next());
+        assert_eq!(0, Register::<u16>(0, 0).set_bits_iter().count());
+    }
+
+    #[test]
+    fn set_bit_scan() {
+        let r = Register::<u16>(0, 0);
+        assert_eq!(None, r.lowest_set_bit());
+        assert_eq!(None, r.highest_set_bit());
+
+        let r = Register::<u16>(0, 0x0010);
+        assert_eq!(Some(4), r.lowest_set_bit());
+        assert_eq!(Some(4), r.highest_set_bit());
+
+        let r = Register::<u16>(0, 0x8106);
+        assert_eq!(Some(1), r.lowest_set_bit());
+        assert_eq!(Some(15), r.highest_set_bit());
+    }
+
     #[test]
     fn get_masked() {
-        let mut r = Register::<u16>(0, 0xFAF0);
+        let r = Register::<u16>(0, 0xFAF0);
         assert_eq!(0x00, r.get_masked(0, 0xf));
         assert_eq!(0x0F, r.get_masked(4, 0xf));
         assert_eq!(0xFA, r.get_masked(8, 0xff));
     }
 
+    #[test]
+    fn fields() {
+        type Mode = Field<0, 2>;
+        type Div = Field<4, 4>;
+        assert_eq!(0b11, Mode::MASK);
+        assert_eq!(0b1111, Div::MASK);
+        assert_eq!(!0, Field::<0, 64>::MASK);
+
+        let r = Register::<u16>(0, 0xFFFF).write_field::<Mode>(0b01).write_field::<Div>(0xA);
+        assert_eq!(0xFFAD, r.value());
+        assert_eq!(0b01, r.read_field::<Mode>());
+        assert_eq!(0xA, r.read_field::<Div>());
+
+        let r = r.write_field::<Div>(0x13);
+        assert_eq!(0x3, r.read_field::<Div>());
+        assert_eq!(0b01, r.read_field::<Mode>());
+    }
+
+    #[test]
+    fn set_masked_typed() {
+        let r = Register::<u16>(0, 0xF00F);
+        let r = r.set_masked_typed(Shift(4), Mask(0xFF), FieldVal(0xAB));
+        assert_eq!(0xFABF, r.value());
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn trace_hook() {
+        use std::sync::Mutex;
+        use std::vec::Vec;
+        use ::register::set_trace_hook;
+
+        static WRITES: Mutex<Vec<(usize, u64)>> = Mutex::new(Vec::new());
+        fn record(addr: usize, value: u64) {
+            WRITES.lock().unwrap().push((addr, value));
+        }
+
+        let mut a: u16 = 0;
+        let mut b: u32 = 0;
+        let (a_addr, b_addr) = (&mut a as *mut u16 as usize, &mut b as *mut u32 as usize);
+
+        let _lock = ::register::TRACE_TEST_LOCK.lock().unwrap();
+        set_trace_hook(Some(record));
+        Register::<u16>(a_addr, 0x1234).write();
+        Register::<u32>(b_addr, 0xAABB_CCDD).write();
+        Register::<u16>(a_addr, 0x0550).write_preserving(0xF00F);
+        #[cfg(feature = "atomics")]
+        assert_eq!(Ok(0xAABB_CCDD), Register::<u32>::new(b_addr).compare_exchange(0xAABB_CCDD, 0x10));
+        set_trace_hook(None);
+        Register::<u16>(a_addr, 0x5678).write();
+
+        let writes: Vec<_> = WRITES.lock().unwrap().iter().cloned()
+            .filter(|w| w.0 == a_addr || w.0 == b_addr).collect();
+        let expected = std::vec![
+            (a_addr, 0x1234),
+            (b_addr, 0xAABB_CCDD),
+            (a_addr, 0x1554),
+            #[cfg(feature = "atomics")]
+            (b_addr, 0x10),
+        ];
+        assert_eq!(expected, writes);
+        assert_eq!(0x5678, a);
+    }
+
+    #[test]
+    fn into_raw() {
+        let r = Register::<u32>::with(0x4000_0000, 0xAABB_CCDD);
+        assert_eq!(0x4000_0000, r.addr());
+        let raw: u32 = r.into();
+        assert_eq!(0xAABB_CCDD, raw);
+        assert_eq!(0x12, u8::from(Register::<u8>::with(0, 0x12)));
+    }
+
+    #[test]
+    fn scaled() {
+        let r = Register::<u32>(0, 0x0030);
+        assert_eq!(Scaled(3_000_000), r.get_scaled(4, 0xF, 1_000_000));
+        let r = r.set_scaled(4, 0xF, Scaled(7_000_000), 1_000_000);
+        assert_eq!(0x0070, r.value());
+        assert_eq!(0x0070, r.set_scaled(4, 0xF, Scaled(7_999_999), 1_000_000).value());
+    }
+
+    #[test]
+    #[should_panic(expected = "divide by zero")]
+    fn set_scaled_zero_factor() {
+        Register::<u32>(0, 0).set_scaled(4, 0xF, Scaled(1), 0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overflow")]
+    fn get_scaled_overflow() {
+        Register::<u8>(0, 0xF0).get_scaled(4, 0xF, 32);
+    }
+
+    #[test]
+    fn write_if_changed() {
+        let mut mem: u16 = 0x1234;
+        let r = Register::<u16>::new(&mut mem as *mut u16 as usize);
+        assert_eq!(false, r.fresh().set(0x1234).write_if_changed());
+        assert_eq!(true, r.fresh().set(0x5678).write_if_changed());
+        assert_eq!(0x5678, r.clone().read().value());
+    }
+
+    #[test]
+    fn set_fields() {
+        const PROGRAM: [(u16, u16, u16); 2] = [(0, 0b11, 0b10), (4, 0xF, 0xA)];
+        let r = Register::<u16>(0, 0xF00F).set_fields(&PROGRAM);
+        assert_eq!(0xF0AE, r.value());
+        assert_eq!(0xF00F, Register::<u16>(0, 0xF00F).set_fields(&[]).value());
+    }
+
+    #[test]
+    fn set_bit_active_low() {
+        let r = Register::<u16>(0, 0xFFFF).set_bit_active_low(3, true);
+        assert_eq!(0xFFF7, r.value());
+        let r = r.set_bit_active_low(3, false).set_bit_active_low(0, true);
+        assert_eq!(0xFFFE, r.value());
+    }
+
+    #[test]
+    fn insert() {
+        let r = Register::<u16>(0, 0xF0F0).insert(0x0FF0, 0x0A50);
+        assert_eq!(0xFA50, r.value());
+        let r = r.insert(0x00F0, 0xFF30);
+        assert_eq!(0xFA30, r.value());
+    }
+
+    #[test]
+    fn get_masked_signed() {
+        let r = Register::<u16>(0, 0b0111_1111_0000);
+        assert_eq!(-1, r.get_masked_signed(4, 0b1111));
+        assert_eq!(7, r.get_masked_signed(8, 0b1111));
+        assert_eq!(0x7F, r.get_masked_signed(4, 0xFF));
+        assert_eq!(-2048, Register::<u16>(0, 0x0800).get_masked_signed(0, 0xFFF));
+        assert_eq!(0, r.get_masked_signed(4, 0));
+    }
+
+    #[test]
+    fn increment_decrement() {
+        let r = Register::<u8>(0, 0xFE).increment();
+        assert_eq!(0xFF, r.value());
+        let r = r.increment();
+        assert_eq!(0x00, r.value());
+        let r = r.decrement();
+        assert_eq!(0xFF, r.value());
+        assert_eq!(0x0F, Register::<u8>(0, 0x10).decrement().value());
+    }
+
+    #[test]
+    fn set_masked_checked() {
+        let r = Register::<u16>(0, 0x0000);
+        let r = r.set_masked_checked(4, 0xF, 0xA).unwrap();
+        assert_eq!(0x00A0, r.value());
+        assert_eq!(Err(0x1A), r.set_masked_checked(8, 0xF, 0x1A));
+    }
+
+    #[test]
+    fn write_preserving() {
+        let mut mem: u16 = 0xA00F;
+        let mut r = Register::<u16>::new(&mut mem as *mut u16 as usize);
+        r = r.set(0x0550);
+        r.write_preserving(0xF00F);
+        assert_eq!(0xA55F, r.read().value());
+    }
+
+    #[cfg(feature = "atomics")]
+    #[test]
+    fn compare_exchange() {
+        let mut mem: u32 = 0x10;
+        let r = Register::<u32>::new(&mut mem as *mut u32 as usize);
+        assert_eq!(Ok(0x10), r.compare_exchange(0x10, 0x20));
+        assert_eq!(Err(0x20), r.compare_exchange(0x10, 0x30));
+        assert_eq!(0x20, mem);
+    }
+
+    #[test]
+    fn wait_for_masked() {
+        let mut mem: u16 = 0x0020;
+        let mut r = Register::<u16>::new(&mut mem as *mut u16 as usize);
+        assert_eq!(Ok(()), r.wait_for_masked(4, 0b11, 0b10, 1));
+        assert_eq!(Err(()), r.wait_for_masked(4, 0b11, 0b11, 10));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "bit index out of range")]
+    fn get_bit_out_of_range() {
+        let r = Register::<u16>(0, 0);
+        r.get_bit(16);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "bit index out of range")]
+    fn set_bit_out_of_range() {
+        let r = Register::<u8>(0, 0);
+        r.set_bit(8, true);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "shift out of range")]
+    fn get_masked_out_of_range() {
+        let r = Register::<u32>(0, 0);
+        r.get_masked(32, 0b1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "shift out of range")]
+    fn set_masked_out_of_range() {
+        let r = Register::<u16>(0, 0);
+        r.set_masked(20, 0b1, 1);
+    }
+
+    #[test]
+    fn masked_in_range() {
+        let r = Register::<u16>(0, 0).set_masked(15, 0b1, 1);
+        assert_eq!(0x8000, r.value());
+        assert_eq!(1, r.get_masked(15, 0b1));
+        assert_eq!(true, r.get_bit(15));
+    }
+
     #[test]
     fn set_masked() {
         let mut r = Register::<u16>(0, 0x0000);
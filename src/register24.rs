@@ -0,0 +1,121 @@
+// 24-bit register type for packed three byte registers
+// Copyright 2018 Ryan Kurte
+
+use core::ptr::{read_volatile, write_volatile};
+
+#[doc = "Mask covering the 24 bits of a `Register24` value"]
+pub const MASK_24: u32 = 0x00FF_FFFF;
+
+// 24-bit register helper structure
+// There is no native 24-bit type, so values are held in a u32 and accessed as three bytes.
+#[doc = "Register24 type wraps the address and value of a packed 24-bit register, such as an RGB888 colour register"]
+#[doc = "Registers are accessed as three volatile byte reads or writes, least significant byte first at the"]
+#[doc = "register address, use `read_be` and `write_be` for registers storing the most significant byte first."]
+#[doc = "No bytes outside the three at the register address are touched"]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Register24(pub usize, pub u32);
+
+impl Register24 {
+    #[doc = "Creates a new 24-bit register with the specified address"]
+    pub fn new(addr: usize) -> Register24 {
+        Register24(addr, 0)
+    }
+
+    #[doc = "Returns the register address"]
+    pub fn addr(&self) -> usize {
+        self.0
+    }
+
+    #[doc = "returns the register value"]
+    pub fn value(&self) -> u32 {
+        self.1
+    }
+
+    #[doc = "sets the internal value of the register, discarding bits above the 24 bit width"]
+    pub fn set(mut self, val: u32) -> Register24 {
+        self.1 = val & MASK_24;
+        self
+    }
+
+    #[doc = "Fetches a value with the provided mask and shift"]
+    pub fn get_masked(&self, shift: u32, mask: u32) -> u32 {
+        read_masked!(self.1, shift, mask)
+    }
+
+    #[doc = "Sets a value with a provided mask and shift, discarding bits above the 24 bit width"]
+    pub fn set_masked(mut self, shift: u32, mask: u32, val: u32) -> Register24 {
+        write_masked!(self.1, shift, mask, val);
+        self.1 &= MASK_24;
+        self
+    }
+
+    #[doc = "Reads the little-endian register value and returns a new instance with internal value set"]
+    pub fn read(&mut self) -> Register24 {
+        Register24(self.0, u32::from_le_bytes(self.read_bytes()))
+    }
+
+    #[doc = "Reads the big-endian register value and returns a new instance with internal value set"]
+    pub fn read_be(&mut self) -> Register24 {
+        let b = self.read_bytes();
+        Register24(self.0, u32::from_be_bytes([0, b[0], b[1], b[2]]))
+    }
+
+    #[doc = "Writes the internal value to the register, least significant byte first"]
+    pub fn write(self) {
+        let b = self.1.to_le_bytes();
+        self.write_bytes([b[0], b[1], b[2]])
+    }
+
+    #[doc = "Writes the internal value to the register, most significant byte first"]
+    pub fn write_be(self) {
+        let b = self.1.to_be_bytes();
+        self.write_bytes([b[1], b[2], b[3]])
+    }
+
+    // Reads the three register bytes in address order, padded to four bytes with a trailing zero
+    fn read_bytes(&self) -> [u8; 4] {
+        let p = self.0 as *const u8;
+        unsafe {
+            [read_volatile(p), read_volatile(p.add(1)), read_volatile(p.add(2)), 0]
+        }
+    }
+
+    // Writes the three register bytes in address order
+    fn write_bytes(&self, b: [u8; 3]) {
+        let p = self.0 as *mut u8;
+        unsafe {
+            write_volatile(p, b[0]);
+            write_volatile(p.add(1), b[1]);
+            write_volatile(p.add(2), b[2]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::register24::Register24;
+
+    #[test]
+    fn round_trip() {
+        let mut mem = [0xAAu8; 5];
+        let mut r = Register24::new(&mut mem[1] as *mut u8 as usize);
+
+        r.clone().set(0x12_3456).write();
+        assert_eq!([0xAA, 0x56, 0x34, 0x12, 0xAA], mem);
+        assert_eq!(0x12_3456, r.read().value());
+
+        r.clone().set(0x12_3456).write_be();
+        assert_eq!([0xAA, 0x12, 0x34, 0x56, 0xAA], mem);
+        assert_eq!(0x12_3456, r.read_be().value());
+    }
+
+    #[test]
+    fn clamped() {
+        let r = Register24::new(0).set(0xFFAB_CDEF);
+        assert_eq!(0xAB_CDEF, r.value());
+
+        let r = r.set_masked(16, 0xFFFF, 0x1234);
+        assert_eq!(0x34_CDEF, r.value());
+        assert_eq!(0x34, r.get_masked(16, 0xFF));
+    }
+}
@@ -0,0 +1,134 @@
+// Typestate register wrapper for tracking read and fresh values
+// Copyright 2018 Ryan Kurte
+
+use core::marker::PhantomData;
+
+use ::register::{Register, RegisterType};
+
+#[doc = "Fresh marks a register whose internal value was built without reading the register"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Fresh;
+
+#[doc = "Read marks a register whose internal value was read from the register and only modified in place"]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Read;
+
+// Stateful register helper structure
+// This wraps a plain register, tracking the origin of the internal value in the type.
+#[doc = "StatefulRegister type wraps a register, tracking whether the internal value was built from scratch (`Fresh`)"]
+#[doc = "or read from hardware (`Read`), so operations that rely on a prior read are only available once the register has been read."]
+#[doc = "Replacing the value (`set`, `zero`, `fresh`) returns to the `Fresh` state, while `update` keeps the current state."]
+#[doc = ""]
+#[doc = "Note that this is a separate opt-in type so `Register` keeps its two-field tuple layout"]
+#[doc = "```compile_fail"]
+#[doc = "# use embedded_builder::state::StatefulRegister;"]
+#[doc = "let mut r = StatefulRegister::<u32>::new(0x4000_0000).set(1);"]
+#[doc = "r.write_preserving(0xFF00);"]
+#[doc = "```"]
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatefulRegister<T: RegisterType<T>, S = Fresh> (Register<T>, PhantomData<S>);
+
+impl <T: RegisterType<T>>StatefulRegister<T> {
+    #[doc = "Creates a new fresh register of the provided type with the specified address"]
+    pub fn new(addr: usize) -> StatefulRegister<T> {
+        StatefulRegister(Register::new(addr), PhantomData)
+    }
+}
+
+impl <T: RegisterType<T>, S>StatefulRegister<T, S> {
+    #[doc = "Returns the register address"]
+    pub fn addr(&self) -> usize {
+        self.0.addr()
+    }
+
+    #[doc = "Returns the internal register value"]
+    pub fn value(&self) -> T {
+        self.0.value()
+    }
+
+    #[doc = "Returns the wrapped register"]
+    pub fn register(&self) -> &Register<T> {
+        &self.0
+    }
+
+    #[doc = "Consumes the wrapper, returning the wrapped register"]
+    pub fn into_register(self) -> Register<T> {
+        self.0
+    }
+
+    #[doc = "Reads the register value and returns a new instance in the `Read` state"]
+    pub fn read(&mut self) -> StatefulRegister<T, Read> {
+        StatefulRegister(self.0.read(), PhantomData)
+    }
+
+    #[doc = "Returns a zeroed fresh register at the same address, without reading the register"]
+    pub fn fresh(&self) -> StatefulRegister<T> {
+        StatefulRegister(self.0.fresh(), PhantomData)
+    }
+
+    #[doc = "Replaces the internal value, returning a fresh register"]
+    pub fn set(self, val: T) -> StatefulRegister<T> {
+        StatefulRegister(self.0.set(val), PhantomData)
+    }
+
+    #[doc = "Clears the internal value, returning a fresh register"]
+    pub fn zero(mut self) -> StatefulRegister<T> {
+        StatefulRegister(self.0.zero(), PhantomData)
+    }
+
+    #[doc = "Applies the provided builder function to the internal value, keeping the current state"]
+    #[doc = "Note that the function should modify rather than replace the value to keep the `Read` state meaningful"]
+    pub fn update<F: FnOnce(Register<T>) -> Register<T>>(self, f: F) -> StatefulRegister<T, S> {
+        StatefulRegister(f(self.0), PhantomData)
+    }
+
+    #[doc = "Writes the internal value to the register"]
+    pub fn write(self) {
+        self.0.write()
+    }
+}
+
+impl <T: RegisterType<T>>StatefulRegister<T, Read> {
+    #[doc = "Writes the internal value to the register, preserving the bits under the reserved mask"]
+    #[doc = "This is only available on registers that have been read, so fresh values are not silently merged"]
+    pub fn write_preserving(&mut self, reserved_mask: T) {
+        self.0.write_preserving(reserved_mask)
+    }
+}
+
+impl <T: RegisterType<T>, S>From<StatefulRegister<T, S>> for Register<T> {
+    fn from(r: StatefulRegister<T, S>) -> Register<T> {
+        r.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::register::Register;
+    use ::state::{StatefulRegister, Fresh, Read};
+
+    #[test]
+    fn state_transitions() {
+        let mut mem: u16 = 0x1234;
+        let mut r = StatefulRegister::<u16>::new(&mut mem as *mut u16 as usize);
+        let read: StatefulRegister<u16, Read> = r.read().update(|r| r.set_bit(0, true));
+        assert_eq!(0x1235, read.value());
+        assert!(read.register().value_eq(&Register::<u16>(0, 0x1235)));
+
+        let fresh: StatefulRegister<u16, Fresh> = read.clone().set(0x0001);
+        assert_eq!(0x0001, fresh.value());
+        let fresh: StatefulRegister<u16, Fresh> = read.clone().zero();
+        assert_eq!(0x0000, fresh.value());
+        assert_eq!(0x0000, read.fresh().value());
+    }
+
+    #[test]
+    fn write_preserving() {
+        let mut mem: u16 = 0xA00F;
+        let mut r = StatefulRegister::<u16>::new(&mut mem as *mut u16 as usize);
+        let mut r = r.read().update(|r| r.clear(0x0FF0).or(0x0550));
+        r.write_preserving(0xF00F);
+        assert_eq!(0xA55F, r.read().value());
+        assert_eq!(0xA55F, Register::from(r).value());
+    }
+}
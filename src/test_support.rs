@@ -0,0 +1,162 @@
+// Test support types for verifying driver register access sequences
+// Copyright 2018 Ryan Kurte
+
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use std::vec::Vec;
+
+use ::access::RegisterAccess;
+use ::register::RegisterType;
+
+#[doc = "TraceEntry describes a single register access as `(address, value, is_read)`"]
+pub type TraceEntry = (usize, u64, bool);
+
+// Register trace helper structure
+// This wraps another accessor, recording each access before passing it through.
+#[doc = "RegisterTrace type wraps a register accessor, recording every read and write for later verification"]
+#[doc = "Drivers using `read_with` and `write_with` can then be checked against a golden access sequence"]
+#[doc = "with `assert_sequence` in host tests. Note that with the `verify-writes` feature enabled in builds with"]
+#[doc = "debug assertions the read-back following each write is also recorded"]
+pub struct RegisterTrace<T: RegisterType<T>, A: RegisterAccess<T>> {
+    inner: A,
+    accesses: RefCell<Vec<TraceEntry>>,
+    _t: PhantomData<T>,
+}
+
+impl <T: RegisterType<T>, A: RegisterAccess<T>>RegisterTrace<T, A> {
+    #[doc = "Creates a new trace wrapping the provided accessor, ie. a fake register file"]
+    pub fn new(inner: A) -> RegisterTrace<T, A> {
+        RegisterTrace{ inner, accesses: RefCell::new(Vec::new()), _t: PhantomData }
+    }
+
+    #[doc = "Returns the wrapped accessor"]
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    #[doc = "Returns a copy of the recorded accesses in order"]
+    pub fn accesses(&self) -> Vec<TraceEntry> {
+        self.accesses.borrow().clone()
+    }
+
+    #[doc = "Clears the recorded accesses"]
+    pub fn clear(&mut self) {
+        self.accesses.borrow_mut().clear()
+    }
+
+    #[doc = "Asserts the recorded accesses exactly match the expected sequence"]
+    #[doc = "Panics with the index and details of the first differing access if they do not"]
+    pub fn assert_sequence(&self, expected: &[TraceEntry]) {
+        let actual = self.accesses.borrow();
+        for (i, (a, e)) in actual.iter().zip(expected.iter()).enumerate() {
+            if a != e {
+                panic!("register access {} mismatch: expected {}, found {}", i, Self::describe(e), Self::describe(a));
+            }
+        }
+        if actual.len() != expected.len() {
+            let n = actual.len().min(expected.len());
+            match actual.get(n) {
+                Some(a) => panic!("register access {} unexpected: found {} after {} expected accesses", n, Self::describe(a), expected.len()),
+                None => panic!("register access {} missing: expected {} but only {} accesses recorded", n, Self::describe(&expected[n]), actual.len()),
+            }
+        }
+    }
+
+    // Formats an access for assertion messages
+    fn describe(e: &TraceEntry) -> std::string::String {
+        match e.2 {
+            true => std::format!("read 0x{:x} from 0x{:x}", e.1, e.0),
+            false => std::format!("write 0x{:x} to 0x{:x}", e.1, e.0),
+        }
+    }
+}
+
+impl <T: RegisterType<T>, A: RegisterAccess<T>>RegisterAccess<T> for RegisterTrace<T, A> {
+    fn read(&self, addr: usize) -> T {
+        let v = self.inner.read(addr);
+        self.accesses.borrow_mut().push((addr, v.into(), true));
+        v
+    }
+
+    fn write(&mut self, addr: usize, v: T) {
+        self.accesses.borrow_mut().push((addr, v.into(), false));
+        self.inner.write(addr, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+    use std::vec::Vec;
+    use ::access::RegisterAccess;
+    use ::register::Register;
+    use ::test_support::{RegisterTrace, TraceEntry};
+
+    // Fake register file backed by an array of word registers
+    struct Fake([u32; 16]);
+
+    impl RegisterAccess<u32> for Fake {
+        fn read(&self, addr: usize) -> u32 {
+            self.0[addr / 4]
+        }
+
+        fn write(&mut self, addr: usize, v: u32) {
+            self.0[addr / 4] = v;
+        }
+    }
+
+    // Sample driver init routine: reset, configure, then enable
+    fn init(access: &mut impl RegisterAccess<u32>) {
+        let ctrl = Register::<u32>::new(0x00);
+        ctrl.fresh().set_bit(31, true).write_with(access);
+        Register::<u32>::new(0x04).set(0x0003).write_with(access);
+        ctrl.read_with(access).set_bit(31, false).set_bit(0, true).write_with(access);
+    }
+
+    // Expected accesses for a write, including the read-back when verifying writes in debug builds
+    fn write(addr: usize, v: u64) -> Vec<TraceEntry> {
+        vec![
+            (addr, v, false),
+            #[cfg(all(feature = "verify-writes", debug_assertions))]
+            (addr, v, true),
+        ]
+    }
+
+    #[test]
+    fn assert_sequence() {
+        let mut t = RegisterTrace::new(Fake([0; 16]));
+        init(&mut t);
+        t.assert_sequence(&[
+            write(0x00, 0x8000_0000),
+            write(0x04, 0x0000_0003),
+            vec![(0x00, 0x8000_0000, true)],
+            write(0x00, 0x0000_0001),
+        ].concat());
+        assert_eq!(1, t.inner().0[0]);
+
+        t.clear();
+        t.assert_sequence(&[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "mismatch: expected write 0x7 to 0x4, found write 0x3 to 0x4")]
+    fn assert_sequence_mismatch() {
+        let mut t = RegisterTrace::new(Fake([0; 16]));
+        init(&mut t);
+        t.assert_sequence(&[
+            write(0x00, 0x8000_0000),
+            write(0x04, 0x0000_0007),
+        ].concat());
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected: found read 0x80000000 from 0x0")]
+    fn assert_sequence_unexpected() {
+        let mut t = RegisterTrace::new(Fake([0; 16]));
+        init(&mut t);
+        t.assert_sequence(&[
+            write(0x00, 0x8000_0000),
+            write(0x04, 0x0000_0003),
+        ].concat());
+    }
+}
@@ -0,0 +1,102 @@
+// Transaction type for ordered multi-register writes
+// Copyright 2018 Ryan Kurte
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use ::register::{Register, RegisterType};
+
+// Transaction helper structure
+// This collects register writes in a fixed capacity buffer and applies them in order on commit.
+#[doc = "Transaction type collects up to N register writes to be applied in order"]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Transaction<T: RegisterType<T>, const N: usize> {
+    writes: [(usize, T); N],
+    len: usize,
+    fence: bool,
+}
+
+impl <T: RegisterType<T>, const N: usize>Default for Transaction<T, N> {
+    fn default() -> Transaction<T, N> {
+        Transaction::new()
+    }
+}
+
+impl <T: RegisterType<T>, const N: usize>Transaction<T, N> {
+    #[doc = "Creates a new empty transaction"]
+    pub fn new() -> Transaction<T, N> {
+        Transaction{ writes: [(0, T::zero()); N], len: 0, fence: false }
+    }
+
+    #[doc = "Sets whether a compiler fence is inserted between each write on commit"]
+    pub fn fenced(mut self, fence: bool) -> Transaction<T, N> {
+        self.fence = fence;
+        self
+    }
+
+    #[doc = "Adds a write of the provided value to the provided register"]
+    #[doc = "Panics if the transaction capacity is exceeded"]
+    pub fn write(mut self, reg: &Register<T>, val: T) -> Transaction<T, N> {
+        assert!(self.len < N, "transaction capacity exceeded");
+        self.writes[self.len] = (reg.addr(), val);
+        self.len += 1;
+        self
+    }
+
+    #[doc = "Returns the number of writes in the transaction"]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[doc = "Returns whether the transaction contains no writes"]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[doc = "Applies the collected writes in the order they were added"]
+    pub fn commit(self) {
+        for (i, &(addr, val)) in self.writes[..self.len].iter().enumerate() {
+            if self.fence && i != 0 {
+                compiler_fence(Ordering::SeqCst);
+            }
+            Register::new(addr).set(val).write();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::register::Register;
+    use ::transaction::Transaction;
+
+    #[test]
+    fn commit() {
+        let mut mem = [0u32; 3];
+        let base = mem.as_mut_ptr() as usize;
+        let (a, b, c) = (Register::<u32>::new(base), Register::<u32>::new(base + 4), Register::<u32>::new(base + 8));
+
+        let t = Transaction::<u32, 4>::new().fenced(true)
+            .write(&a, 0x11)
+            .write(&c, 0x33)
+            .write(&b, 0x22);
+        assert_eq!(3, t.len());
+        t.commit();
+
+        assert_eq!([0x11, 0x22, 0x33], mem);
+    }
+
+    #[test]
+    fn commit_ordered() {
+        let mut mem = 0u32;
+        let r = Register::<u32>::new(&mut mem as *mut u32 as usize);
+
+        Transaction::<u32, 2>::new().write(&r, 1).write(&r, 2).commit();
+        assert_eq!(2, mem);
+    }
+
+    #[test]
+    #[should_panic(expected = "transaction capacity exceeded")]
+    fn capacity() {
+        let r = Register::<u32>::new(0);
+        Transaction::<u32, 1>::new().write(&r, 1).write(&r, 2);
+    }
+}